@@ -1,5 +1,5 @@
-use clap::{Parser, Subcommand};
-use qwxl::pipeline::{Config, Pipeline};
+use clap::{Parser, Subcommand, ValueEnum};
+use qwxl::pipeline::{Config, Pipeline, TargetFormat};
 use std::env;
 use std::path::PathBuf;
 use tracing::debug;
@@ -16,6 +16,19 @@ struct Cli {
     #[arg(long, global = true, value_name = "PATH")]
     qwex_home: Option<PathBuf>,
 
+    /// Treat a `qwex.lock` entry that no longer matches its resolved import
+    /// as a hard error instead of a warning, and leave `qwex.lock` untouched
+    /// instead of re-pinning it.
+    #[arg(long, alias = "locked", global = true)]
+    frozen: bool,
+
+    /// Run any task with a `sandbox: true` annotation inside a fresh
+    /// mount/PID/user namespace. Only `qwex run <file> <task>` honors this;
+    /// the generated script used by `build` and by `run` without a task
+    /// runs every task unsandboxed.
+    #[arg(long, global = true)]
+    sandbox: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -28,6 +41,10 @@ enum Commands {
         #[arg(short, long, value_name = "TARGET")]
         o: Option<PathBuf>,
 
+        /// Output format to render the task graph into
+        #[arg(long, value_enum, default_value_t = TargetFormatArg::Shell)]
+        target: TargetFormatArg,
+
         #[arg(value_name = "FILE")]
         file: PathBuf,
     },
@@ -36,16 +53,82 @@ enum Commands {
         /// Path to qwex.yaml
         #[arg(value_name = "FILE")]
         file: PathBuf,
+
+        /// Run just this one task (plus its dependencies) instead of the
+        /// whole project.
+        #[arg(value_name = "TASK")]
+        task: Option<String>,
+
+        /// Override a prop for this run, as `key=value`. Only meaningful
+        /// alongside TASK; repeatable.
+        #[arg(long = "with", value_name = "KEY=VALUE")]
+        with: Vec<String>,
     },
 }
 
+/// CLI-facing mirror of `qwxl::pipeline::TargetFormat`; kept separate so the
+/// library crate doesn't need a `clap` dependency just for `ValueEnum`.
+#[derive(Clone, Copy, ValueEnum)]
+enum TargetFormatArg {
+    Shell,
+    Makefile,
+}
+
+impl From<TargetFormatArg> for TargetFormat {
+    fn from(value: TargetFormatArg) -> Self {
+        match value {
+            TargetFormatArg::Shell => TargetFormat::Shell,
+            TargetFormatArg::Makefile => TargetFormat::Makefile,
+        }
+    }
+}
+
+/// Subcommand names (and help/version flags) that always win over a
+/// same-named alias, so a project can't accidentally shadow `qwex build`.
+const BUILTIN_COMMANDS: &[&str] = &["build", "run", "help", "-h", "--help", "-V", "--version"];
+
+/// Expands a leading `qwex <alias>` token into the argv it stands for, per
+/// the `aliases:` table in `./qwex.yaml`, before `Cli::parse_from` ever sees
+/// it. Aliases are tried repeatedly (an alias can expand to another alias)
+/// but a name reappearing mid-chain is rejected instead of looping forever;
+/// anything that isn't a recognized alias -- including a missing or
+/// unparsable `qwex.yaml` -- is left for `clap` to reject as usual.
+fn expand_aliases(mut args: Vec<String>) -> anyhow::Result<Vec<String>> {
+    let is_builtin = |tok: &str| BUILTIN_COMMANDS.contains(&tok) || tok.starts_with('-');
+
+    if args.get(1).map(|tok| is_builtin(tok)).unwrap_or(true) {
+        return Ok(args);
+    }
+
+    let yaml_path = env::current_dir()?.join("qwex.yaml");
+    let Ok(aliases) = qwxl::pipeline::load_aliases(&yaml_path) else {
+        return Ok(args);
+    };
+
+    let mut seen = std::collections::HashSet::new();
+    while let Some(expansion) = args.get(1).and_then(|tok| aliases.get(tok)) {
+        let name = args[1].clone();
+        if !seen.insert(name.clone()) {
+            anyhow::bail!("Alias '{}' recurses into itself", name);
+        }
+        let tokens: Vec<String> = expansion.split_whitespace().map(str::to_string).collect();
+        args.splice(1..2, tokens);
+        if args.get(1).map(|tok| is_builtin(tok)).unwrap_or(true) {
+            break;
+        }
+    }
+
+    Ok(args)
+}
+
 fn main() -> anyhow::Result<()> {
     tracing_subscriber::registry()
         .with(fmt::layer())
         .with(EnvFilter::from_default_env())
         .init();
 
-    let cli = Cli::parse();
+    let args = expand_aliases(env::args().collect())?;
+    let cli = Cli::parse_from(args);
 
     let qwex_dir: PathBuf = match cli.qwex_home {
         Some(p) => p,
@@ -54,20 +137,26 @@ fn main() -> anyhow::Result<()> {
 
     let mut config = Config {
         home_dir: qwex_dir,
+        frozen: cli.frozen,
+        sandbox: cli.sandbox,
         ..Default::default()
     };
 
     match cli.command {
-        Some(Commands::Build { o, file }) => {
-            if let Some(target) = o {
-                config.target_path = target;
+        Some(Commands::Build { o, target, file }) => {
+            if let Some(target_path) = o {
+                config.target_path = target_path;
             }
+            config.target_format = target.into();
             config.source_path = file;
             build(config)?;
         }
-        Some(Commands::Run { file }) => {
+        Some(Commands::Run { file, task, with }) => {
             config.source_path = file;
-            run(config)?;
+            match task {
+                Some(task) => run_task(config, &task, parse_overrides(&with)?)?,
+                None => run(config)?,
+            }
         }
         None => {
             run(config)?;
@@ -79,7 +168,7 @@ fn main() -> anyhow::Result<()> {
 
 fn build(config: Config) -> anyhow::Result<()> {
     let mut pipeline = Pipeline::new(config.clone());
-    let script = pipeline.compile()?;
+    let script = pipeline.generate()?;
     debug!("Writing script to: {}", config.target_path.display());
     if let Some(parent) = config.target_path.parent() {
         debug!("Creating parent dir: {}", parent.display());
@@ -90,11 +179,93 @@ fn build(config: Config) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Splits each `--with key=value` flag into a `(key, value)` pair, rejecting
+/// anything without an `=` up front instead of letting it silently become an
+/// empty-value override.
+fn parse_overrides(with: &[String]) -> anyhow::Result<Vec<(String, String)>> {
+    with.iter()
+        .map(|pair| {
+            pair.split_once('=')
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .ok_or_else(|| anyhow::anyhow!("Invalid --with value '{}', expected key=value", pair))
+        })
+        .collect()
+}
+
+/// Classic Wagner-Fischer edit distance, used only to find a "did you mean"
+/// suggestion for an unrecognized task name -- not performance-sensitive, so
+/// the straightforward O(n*m) table is fine.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        let mut prev_diag = row[0];
+        row[0] = i;
+        for j in 1..=b.len() {
+            let cur = row[j];
+            row[j] = if a[i - 1] == b[j - 1] {
+                prev_diag
+            } else {
+                1 + prev_diag.min(row[j]).min(row[j - 1])
+            };
+            prev_diag = cur;
+        }
+    }
+
+    row[b.len()]
+}
+
+/// The candidate in `names` closest to `target` by edit distance, used to
+/// suggest a fix for a mistyped task name on the CLI.
+fn closest_match<'a>(target: &str, names: &'a [String]) -> Option<&'a str> {
+    names
+        .iter()
+        .map(|name| (name.as_str(), levenshtein(target, name)))
+        .min_by_key(|(_, dist)| *dist)
+        .map(|(name, _)| name)
+}
+
+/// `qwex run <file> <task> [--with key=value ...]`: resolves and runs just
+/// `task` (plus its transitive dependencies) in-process via `Pipeline`'s
+/// `Driver`, instead of building and executing the whole project's script.
+fn run_task(config: Config, task: &str, overrides: Vec<(String, String)>) -> anyhow::Result<()> {
+    let mut pipeline = Pipeline::new(config.clone());
+
+    let available = pipeline.task_names(&config.root_alias)?;
+    if !available.iter().any(|t| t == task) {
+        return Err(match closest_match(task, &available) {
+            Some(suggestion) => {
+                anyhow::anyhow!("Task '{}' not found. Did you mean '{}'?", task, suggestion)
+            }
+            None => anyhow::anyhow!("Task '{}' not found.", task),
+        });
+    }
+
+    let report = pipeline.run_task_with_overrides(&config.root_alias, task, &overrides)?;
+    if !report.success {
+        anyhow::bail!("Task '{}' failed", task);
+    }
+    Ok(())
+}
+
+/// `qwex run <file>` with no task named: builds the script, then dispatches
+/// the first task declared on the root module -- mirroring `make`'s default
+/// goal -- since the generated script now requires a `$CMD` to run anything.
 fn run(config: Config) -> anyhow::Result<()> {
     build(config.clone())?;
-    // Execute the script
+
+    let mut pipeline = Pipeline::new(config.clone());
+    let default_task = pipeline
+        .task_names(&config.root_alias)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("'{}' declares no tasks to run", config.source_path.display()))?;
+
     let status = std::process::Command::new("bash")
         .arg(&config.target_path)
+        .arg(&default_task)
         .status()?;
     if !status.success() {
         anyhow::bail!(
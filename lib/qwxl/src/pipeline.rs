@@ -1,20 +1,60 @@
 use crate::pipeline::{
     ast::MetaModule,
     cache::Store,
+    driver::{Driver, DriverReport},
+    emitter::ShellGenerator,
     error::PipelineError,
+    generator::Generator,
+    makefile::MakefileGenerator,
     renderer::{RenderTarget, Resource, SCRIPT_TEMPLATE_NAME, SCRIPT_TEMPLATE_SOURCE},
+    resolver::TaskNode,
 };
 use ahash::HashSet;
 use minijinja::Environment;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::{fs::OpenOptions, path::PathBuf, sync::Arc};
 
 mod ast;
 mod cache;
+pub mod driver;
+pub mod emitter;
 mod error;
+pub mod generator;
+pub mod graph;
 mod loader;
+mod lockfile;
+pub mod makefile;
 mod parser;
+mod remote;
 mod renderer;
+mod resolver;
+mod sandbox;
+
+/// Selects which `Generator` backend `Pipeline::generate` renders the
+/// resolved task graph through.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TargetFormat {
+    /// A self-contained bash script with its own jobserver-based scheduler.
+    #[default]
+    Shell,
+    /// A `Makefile` whose rules reuse `make`'s native parallelism and
+    /// up-to-date checks instead of shipping a bespoke scheduler.
+    Makefile,
+}
+
+/// Reads just the root module's `aliases:` table from `path`, without
+/// resolving any `uses:` imports -- the CLI uses this to expand a bare
+/// `qwex <name>` into its argv tokens before `clap` even sees them, which
+/// needs the table long before a full `Pipeline::parse` would make sense.
+pub fn load_aliases(path: &PathBuf) -> Result<std::collections::BTreeMap<String, String>, PipelineError> {
+    let content = std::fs::read_to_string(path)?;
+    let ext = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("yaml");
+    let module = parser::load_source(&content, ext)?;
+    Ok(module.aliases.unwrap_or_default().into_iter().collect())
+}
 
 /// Shared pipeline configuration.
 #[derive(Clone)]
@@ -27,6 +67,23 @@ pub struct Config {
     pub source_path: PathBuf,
     pub enable_cache: bool,
     pub root_alias: String,
+    /// Max number of tasks the generated script may run concurrently, enforced via
+    /// the GNU Make jobserver protocol. Defaults to the available parallelism.
+    pub jobs: usize,
+    /// Bypasses the on-disk RON artifact cache and always performs a full
+    /// parse, even when a matching `<build_dir>/cache/<hash>.ron` is found.
+    pub force: bool,
+    /// Which `Generator` backend `Pipeline::generate` renders through.
+    pub target_format: TargetFormat,
+    /// The CLI's `--frozen`/`--locked` flag: a `qwex.lock` entry that no
+    /// longer matches the freshly resolved import's content hash becomes a
+    /// hard `PipelineError::LockfileMismatch` instead of a warning, and
+    /// `qwex.lock` is left untouched rather than re-pinned.
+    pub frozen: bool,
+    /// Master switch for namespace-isolated task execution. A task still
+    /// needs its own `sandbox: true` annotation to actually run isolated;
+    /// this flag alone just makes that annotation meaningful.
+    pub sandbox: bool,
 }
 
 impl Config {
@@ -77,19 +134,26 @@ impl Default for Config {
             source_path: PathBuf::from("qwex.yaml"),
             enable_cache: true,
             root_alias: "root".to_string(),
+            jobs: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            force: false,
+            target_format: TargetFormat::default(),
+            frozen: false,
+            sandbox: false,
             cwd,
         }
     }
 }
 
 /// Aggregate stores used by the pipeline.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct PipelineStore {
     pub content: Store<PathBuf, String>,
     pub metamodules: Store<u64, MetaModule>,
     pub aliases: Store<String, u64>,
     pub rendered: Store<RenderTarget, Resource>,
-    pub 
+    pub tasks: Store<u64, TaskNode>,
 }
 
 impl Default for PipelineStore {
@@ -99,6 +163,7 @@ impl Default for PipelineStore {
             metamodules: Store::new(),
             aliases: Store::new(),
             rendered: Store::new(),
+            tasks: Store::new(),
         }
     }
 }
@@ -109,6 +174,14 @@ impl PipelineStore {
             .get(alias)
             .and_then(|hash| self.metamodules.get(hash))
     }
+
+    /// Reverse lookup of `aliases`: the alias name bound to `hash`, if any.
+    pub fn alias_for_hash(&self, hash: u64) -> Option<&str> {
+        self.aliases
+            .iter()
+            .find(|(_, h)| h.as_ref() == &hash)
+            .map(|(alias, _)| alias.as_str())
+    }
 }
 
 pub struct Pipeline {
@@ -138,30 +211,139 @@ impl Pipeline {
     }
 
     pub fn compile(&mut self) -> Result<String, PipelineError> {
+        if self.config.enable_cache && !self.config.force {
+            if let Some(cached) = self.load_cache()? {
+                self.stores = cached;
+                return Ok(("script").to_string());
+            }
+        }
+
         let _ = self.parse()?;
+        self.check_lockfile()?;
+        if !self.config.frozen {
+            self.write_lockfile()?;
+        }
 
-        // Example Cache Serialization (optional)
         if self.config.enable_cache {
-            let artifacts =
-                ron::ser::to_string_pretty(&self.stores, ron::ser::PrettyConfig::default())?;
-            let content: Vec<(&PathBuf, &Arc<String>)> = self.stores.content.0.iter().collect();
-            let hash = ahash::RandomState::default().hash_one(content);
-            let cache_file_name = format!("{:x}.ron", hash);
-            let cache_dir = self.config.build_dir.join("cache");
-            let cache_path = cache_dir.join(cache_file_name);
-
-            std::fs::create_dir_all(&cache_dir)?;
-            OpenOptions::new()
-                .create(true)
-                .write(true)
-                .truncate(true)
-                .open(&cache_path)
-                .and_then(|mut f| {
-                    use std::io::Write;
-                    f.write_all(artifacts.as_bytes())
-                })?;
+            self.write_cache()?;
         }
 
         Ok(("script").to_string())
     }
+
+    /// Parses the source (reusing the cache when possible) and renders the
+    /// resolved task graph through the `Generator` selected by
+    /// `Config.target_format`.
+    pub fn generate(&mut self) -> Result<String, PipelineError> {
+        self.compile()?;
+
+        let generator: Box<dyn Generator> = match self.config.target_format {
+            TargetFormat::Shell => Box::new(ShellGenerator::new()),
+            TargetFormat::Makefile => Box::new(MakefileGenerator::new()),
+        };
+        generator.generate(self)
+    }
+
+    /// Resolves `task` under `alias` and runs it, plus every transitive
+    /// dependency, through a `Driver` sized to `Config.jobs` workers.
+    pub fn run_task(&mut self, alias: &str, task: &str) -> Result<DriverReport, PipelineError> {
+        self.run_task_with_overrides(alias, task, &[])
+    }
+
+    /// Like `run_task`, but layers CLI-supplied `--with key=value` overrides
+    /// on top of the task's declared props before compiling (see
+    /// `resolve_task_with_overrides`).
+    pub fn run_task_with_overrides(
+        &mut self,
+        alias: &str,
+        task: &str,
+        overrides: &[(String, String)],
+    ) -> Result<DriverReport, PipelineError> {
+        self.compile()?;
+        let node = self.resolve_task_with_overrides(alias, task, overrides)?;
+        let driver = Driver::new(
+            self.config.jobs.max(1),
+            self.config.sandbox,
+            self.config.get_build_dir(),
+        );
+        driver.run(&node, &self.stores)
+    }
+
+    /// Every task name declared on `alias`'s root module. Used by the CLI to
+    /// suggest a close match when `qwex run <file> <task>` names a task that
+    /// doesn't exist. Runs `compile` on every call, which reuses the on-disk
+    /// artifact cache when it is valid.
+    pub fn task_names(&mut self, alias: &str) -> Result<Vec<String>, PipelineError> {
+        self.compile()?;
+        let hash = self
+            .stores
+            .aliases
+            .get(alias)
+            .ok_or_else(|| PipelineError::ModuleNotFound(alias.to_string()))?;
+        let meta = self
+            .stores
+            .metamodules
+            .get(hash)
+            .ok_or_else(|| PipelineError::ModuleNotFound(alias.to_string()))?;
+        Ok(meta.module.tasks.keys().cloned().collect())
+    }
+
+    /// The cache is keyed by the root source file's content hash, since that's
+    /// the only thing we can compute before parsing has even started.
+    fn cache_path_for_root(&self) -> Result<(PathBuf, u64), PipelineError> {
+        let root_path = std::fs::canonicalize(self.config.get_source_path())?;
+        let root_content = std::fs::read_to_string(&root_path)?;
+        let hash = parser::str_hash(&root_content);
+        let cache_path = self
+            .config
+            .get_build_dir()
+            .join("cache")
+            .join(format!("{:x}.ron", hash));
+        Ok((cache_path, hash))
+    }
+
+    /// Looks for a previously written `<build_dir>/cache/<hash>.ron` and returns
+    /// the cached store, but only if every file it recorded (by path) still
+    /// matches what's on disk; any mismatch or missing file falls back to `None`
+    /// so the caller performs a full recompile.
+    fn load_cache(&mut self) -> Result<Option<PipelineStore>, PipelineError> {
+        let (cache_path, _) = self.cache_path_for_root()?;
+        if !cache_path.exists() {
+            return Ok(None);
+        }
+
+        let raw = std::fs::read_to_string(&cache_path)?;
+        let cached: PipelineStore = ron::de::from_str(&raw)?;
+
+        for (path, content) in cached.content.0.iter() {
+            match std::fs::read_to_string(path) {
+                Ok(current) if &current == content.as_ref() => {}
+                _ => return Ok(None),
+            }
+        }
+
+        Ok(Some(cached))
+    }
+
+    fn write_cache(&self) -> Result<(), PipelineError> {
+        let (cache_path, _) = self.cache_path_for_root()?;
+        let artifacts =
+            ron::ser::to_string_pretty(&self.stores, ron::ser::PrettyConfig::default())?;
+
+        let cache_dir = cache_path.parent().ok_or_else(|| {
+            PipelineError::Internal("Cache path has no parent directory".to_string())
+        })?;
+        std::fs::create_dir_all(cache_dir)?;
+        OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&cache_path)
+            .and_then(|mut f| {
+                use std::io::Write;
+                f.write_all(artifacts.as_bytes())
+            })?;
+
+        Ok(())
+    }
 }
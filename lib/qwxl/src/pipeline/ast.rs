@@ -15,8 +15,22 @@ pub struct MetaModule {
     #[serde(skip)]
     pub hash: u64,
 
+    /// Full SHA-256 digest (hex) of this module's source content, stable
+    /// across processes and machines, of which `hash` is the truncated form.
+    /// `qwex.lock` pins `hash`, not this digest.
+    #[serde(default)]
+    pub digest: String,
+
     #[serde(skip)]
     pub path_buf: std::path::PathBuf,
+
+    /// The original `uses:` spec this module was fetched from when it's a
+    /// remote import (`git+...#rev` or a bare URL). `None` for a local file,
+    /// where `path_buf` alone is already a stable, human-meaningful identity.
+    /// Used to key lockfile entries so they stay portable across machines
+    /// instead of pinning a machine-local cache path.
+    #[serde(default)]
+    pub origin: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,10 +49,45 @@ pub struct Module {
     #[serde(default)]
     pub tasks: IHashMap<String, Task>,
 
+    /// User-defined CLI shortcuts, e.g. `deploy: "run --with env=prod
+    /// deploy"`. Only meaningful on the root module; the CLI expands a bare
+    /// `qwex <name>` into these argv tokens before `clap` parses them, and a
+    /// built-in subcommand of the same name always takes precedence.
+    #[serde(default)]
+    pub aliases: Option<IHashMap<String, String>>,
+
     #[serde(flatten, default)]
     pub modules: IHashMap<String, Module>,
 }
 
+/// A declared type for a `Task` parameter, used both to type-check/coerce
+/// values passed via `{{ tasks.foo(name=value) }}` and, for root tasks, to
+/// validate CLI flag values in the generated dispatcher.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParamType {
+    #[default]
+    String,
+    Int,
+    Bool,
+    Path,
+}
+
+/// A single declared parameter in a `Task`'s argument list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Param {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub ty: ParamType,
+    /// Literal default used when a call site or CLI flag omits this parameter.
+    #[serde(default)]
+    pub default: Option<String>,
+    /// When true and no `default` is set, omitting this argument at the call
+    /// site is a `PipelineError::InvalidArgRef` rather than silently unbound.
+    #[serde(default)]
+    pub required: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Task {
     pub uses: Option<UseRef>,
@@ -46,6 +95,31 @@ pub struct Task {
     pub props: Option<Props>,
     #[serde(default, alias = "command", alias = "run")]
     pub cmd: String,
+    /// Glob patterns for files this task reads. Their content feeds the task's
+    /// stamp hash so changes to any of them invalidate cached results.
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    /// Glob patterns for files this task is expected to produce. A cached
+    /// stamp is only honored if every declared output still exists.
+    #[serde(default)]
+    pub outputs: Vec<String>,
+    /// Declared parameter schema. Call-site values for these names are
+    /// type-checked/coerced against `ty`, and for root tasks the generated
+    /// dispatcher additionally surfaces them as `--name=value` CLI flags.
+    #[serde(default, alias = "params")]
+    pub args: Vec<Param>,
+    /// Names of sibling tasks (in this same module, or its `uses` target)
+    /// that must be resolved before this one. Folded into the resolved
+    /// `TaskNode.deps` alongside whatever `{{ tasks.foo() }}` calls discover
+    /// on their own, so a task can declare an ordering dependency without
+    /// actually calling the other task from its `cmd`.
+    #[serde(default)]
+    pub needs: Vec<String>,
+    /// Opts this task into namespace-isolated execution when `Config.sandbox`
+    /// is also enabled -- the config flag is the master switch, this
+    /// annotation picks which tasks actually pay for it.
+    #[serde(default)]
+    pub sandbox: bool,
 }
 
 impl Default for Task {
@@ -54,6 +128,11 @@ impl Default for Task {
             props: Some(Props::default()),
             cmd: "".to_string(),
             uses: None,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            args: Vec::new(),
+            needs: Vec::new(),
+            sandbox: false,
         }
     }
 }
@@ -1,11 +1,51 @@
 use ahash::AHashMap;
 use derive_more::{Deref, DerefMut, IntoIterator};
-use serde::Serialize;
-use std::{hash::Hash, sync::Arc};
+use serde::{Deserialize, Serialize};
+use std::{
+    hash::Hash,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use crate::pipeline::error::PipelineError;
+use crate::pipeline::{error::PipelineError, parser::str_hash};
+
+/// Where a `Store` entry keyed by `key` would live on disk under
+/// `cache_dir`: `key`'s own RON encoding, hashed, so any `Serialize` key
+/// works without needing a `Display`/`Hash`-to-filename convention of its
+/// own. Entries are immutable once written (the key's content fully
+/// determines the path), so they're safe to share across runs and machines.
+fn disk_entry_path<K: Serialize>(key: &K, cache_dir: &Path) -> PathBuf {
+    let canonical = ron::ser::to_string(key).unwrap_or_default();
+    cache_dir.join(format!("{:x}.ron", str_hash(&canonical)))
+}
+
+/// Reads a `Store` entry back from its content-addressed path, or `None` on
+/// any miss, I/O error, or decode failure -- a corrupt or absent entry is
+/// just a cache miss, not a hard error.
+pub(crate) fn read_disk_entry<K: Serialize, V: serde::de::DeserializeOwned>(
+    key: &K,
+    cache_dir: &Path,
+) -> Option<V> {
+    let raw = std::fs::read_to_string(disk_entry_path(key, cache_dir)).ok()?;
+    ron::de::from_str(&raw).ok()
+}
+
+/// Persists `value` to its content-addressed path under `cache_dir`.
+/// Best-effort: a write failure just degrades to a cache miss on the next
+/// run rather than failing the build that just computed the value.
+pub(crate) fn write_disk_entry<K: Serialize, V: Serialize>(key: &K, value: &V, cache_dir: &Path) {
+    let path = disk_entry_path(key, cache_dir);
+    if let Some(parent) = path.parent() {
+        if std::fs::create_dir_all(parent).is_err() {
+            return;
+        }
+    }
+    if let Ok(raw) = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()) {
+        let _ = std::fs::write(path, raw);
+    }
+}
 /// A simple, high-performance memory store for pipeline artifacts.
-#[derive(Debug, Serialize, IntoIterator, Deref, DerefMut, Clone)]
+#[derive(Debug, Serialize, Deserialize, IntoIterator, Deref, DerefMut, Clone)]
 #[into_iterator(owned, ref, ref_mut)]
 pub struct Store<K, V>(pub AHashMap<K, Arc<V>>)
 where
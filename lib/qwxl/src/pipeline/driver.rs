@@ -0,0 +1,313 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use ahash::{HashMap, HashSet};
+
+use crate::pipeline::{
+    PipelineStore,
+    error::PipelineError,
+    graph::{find_cycle, topological_order},
+    resolver::TaskNode,
+    sandbox,
+};
+
+/// Outcome of running (or not running) a single resolved task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskStatus {
+    Success,
+    Failed(i32),
+    /// Never ran because one of its dependencies failed.
+    Skipped,
+}
+
+#[derive(Debug, Clone)]
+pub struct TaskResult {
+    pub hash: u64,
+    pub alias: String,
+    pub status: TaskStatus,
+}
+
+#[derive(Debug)]
+pub struct DriverReport {
+    pub results: HashMap<u64, TaskResult>,
+    pub success: bool,
+}
+
+struct SchedulerState {
+    ready: VecDeque<u64>,
+    remaining_deps: HashMap<u64, usize>,
+    results: HashMap<u64, TaskResult>,
+    failed_ancestor: HashSet<u64>,
+    /// Set when a sandboxed task fails to even launch (as opposed to running
+    /// and exiting non-zero). Distinct from a normal `TaskResult::Failed`
+    /// because it's a setup problem, not the task's own outcome, so it's
+    /// surfaced as a hard `PipelineError` from `run`/`run_nodes` instead of
+    /// folded into the report.
+    sandbox_error: Option<PipelineError>,
+}
+
+/// Runs a resolved `TaskNode` DAG with bounded concurrency: each ready node
+/// (every dependency already finished) is handed to one of `workers` worker
+/// threads, its command streamed to `sh -c` (inheriting stdio), and a
+/// dependency's failure marks every downstream task `Skipped` instead of run.
+pub struct Driver {
+    workers: usize,
+    /// Config's master sandbox switch; a per-task `sandbox: true` annotation
+    /// only takes effect when this is also set.
+    sandbox_enabled: bool,
+    /// Parent of each sandboxed task's private work dir.
+    build_dir: PathBuf,
+}
+
+impl Driver {
+    pub fn new(workers: usize, sandbox_enabled: bool, build_dir: PathBuf) -> Self {
+        Driver {
+            workers: workers.max(1),
+            sandbox_enabled,
+            build_dir,
+        }
+    }
+
+    /// Walks `root.deps` through `store.tasks` to collect the full reachable
+    /// subgraph, then schedules and runs it.
+    pub fn run(&self, root: &TaskNode, store: &PipelineStore) -> Result<DriverReport, PipelineError> {
+        let mut nodes: HashMap<u64, Arc<TaskNode>> = HashMap::default();
+        let mut queue: VecDeque<u64> = VecDeque::new();
+        queue.push_back(root.hash);
+        // The root itself may not be in `store.tasks` yet (callers often have
+        // it only as an owned value fresh out of `resolve_task`), so seed it
+        // directly and only look up its dependencies.
+        nodes.insert(root.hash, Arc::new(root.clone()));
+        for dep in &root.deps {
+            queue.push_back(*dep);
+        }
+
+        while let Some(hash) = queue.pop_front() {
+            if nodes.contains_key(&hash) {
+                continue;
+            }
+            let node = store.tasks.get(&hash).ok_or_else(|| {
+                PipelineError::Internal(format!("Task {:x} missing from store", hash))
+            })?;
+            for dep in &node.deps {
+                queue.push_back(*dep);
+            }
+            nodes.insert(hash, node.clone());
+        }
+
+        self.run_nodes(&nodes)
+    }
+
+    /// Schedules and runs an already-collected subgraph directly.
+    pub fn run_nodes(
+        &self,
+        nodes: &HashMap<u64, Arc<TaskNode>>,
+    ) -> Result<DriverReport, PipelineError> {
+        if nodes.is_empty() {
+            return Ok(DriverReport {
+                results: HashMap::default(),
+                success: true,
+            });
+        }
+
+        let display_names: HashMap<u64, String> =
+            nodes.iter().map(|(hash, node)| (*hash, node.alias.clone())).collect();
+        topological_order(nodes)
+            .ok_or_else(|| PipelineError::Cycle(find_cycle(nodes, &display_names)))?;
+
+        let mut dependents: HashMap<u64, Vec<u64>> = HashMap::default();
+        let mut remaining_deps: HashMap<u64, usize> = HashMap::default();
+        for (hash, node) in nodes {
+            remaining_deps.insert(*hash, node.deps.len());
+            for dep in &node.deps {
+                dependents.entry(*dep).or_default().push(*hash);
+            }
+        }
+
+        let ready: VecDeque<u64> = remaining_deps
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(hash, _)| *hash)
+            .collect();
+
+        let total = nodes.len();
+        let state = Arc::new((
+            Mutex::new(SchedulerState {
+                ready,
+                remaining_deps,
+                results: HashMap::default(),
+                failed_ancestor: HashSet::default(),
+                sandbox_error: None,
+            }),
+            Condvar::new(),
+        ));
+        let nodes = Arc::new(nodes.clone());
+        let dependents = Arc::new(dependents);
+
+        let handles: Vec<_> = (0..self.workers)
+            .map(|_| {
+                let state = Arc::clone(&state);
+                let nodes = Arc::clone(&nodes);
+                let dependents = Arc::clone(&dependents);
+                let sandbox_enabled = self.sandbox_enabled;
+                let build_dir = self.build_dir.clone();
+                thread::spawn(move || {
+                    worker_loop(state, nodes, dependents, total, sandbox_enabled, build_dir)
+                })
+            })
+            .collect();
+        for handle in handles {
+            let _ = handle.join();
+        }
+
+        let (lock, _) = &*state;
+        let mut guard = lock.lock().unwrap();
+        if let Some(err) = guard.sandbox_error.take() {
+            return Err(err);
+        }
+        let results = guard.results.clone();
+        let success = results
+            .values()
+            .all(|r| !matches!(r.status, TaskStatus::Failed(_)));
+
+        Ok(DriverReport { results, success })
+    }
+}
+
+fn worker_loop(
+    state: Arc<(Mutex<SchedulerState>, Condvar)>,
+    nodes: Arc<HashMap<u64, Arc<TaskNode>>>,
+    dependents: Arc<HashMap<u64, Vec<u64>>>,
+    total: usize,
+    sandbox_enabled: bool,
+    build_dir: PathBuf,
+) {
+    let (lock, cvar) = &*state;
+    loop {
+        let (hash, skip) = {
+            let mut guard = lock.lock().unwrap();
+            loop {
+                if guard.results.len() == total {
+                    return;
+                }
+                if let Some(hash) = guard.ready.pop_front() {
+                    let skip = guard.failed_ancestor.contains(&hash);
+                    break (hash, skip);
+                }
+                guard = cvar.wait(guard).unwrap();
+            }
+        };
+
+        let node = &nodes[&hash];
+        let status = if skip {
+            TaskStatus::Skipped
+        } else if sandbox_enabled && node.sandbox {
+            match sandbox::run_sandboxed(node, &build_dir) {
+                Ok(status) => status,
+                Err(err) => {
+                    let mut guard = lock.lock().unwrap();
+                    guard.sandbox_error.get_or_insert(err);
+                    drop(guard);
+                    TaskStatus::Failed(-1)
+                }
+            }
+        } else {
+            run_shell(&node.cmd)
+        };
+        let failed = skip || matches!(status, TaskStatus::Failed(_));
+
+        let mut guard = lock.lock().unwrap();
+        guard.results.insert(
+            hash,
+            TaskResult {
+                hash,
+                alias: node.alias.clone(),
+                status,
+            },
+        );
+
+        if let Some(deps) = dependents.get(&hash) {
+            for dependent in deps {
+                if failed {
+                    guard.failed_ancestor.insert(*dependent);
+                }
+                let remaining = guard.remaining_deps.get_mut(dependent).unwrap();
+                *remaining -= 1;
+                if *remaining == 0 {
+                    guard.ready.push_back(*dependent);
+                }
+            }
+        }
+        drop(guard);
+        cvar.notify_all();
+    }
+}
+
+/// Streams `cmd` to `sh -c`, inheriting stdio so output shows up live.
+fn run_shell(cmd: &str) -> TaskStatus {
+    match Command::new("sh").arg("-c").arg(cmd).status() {
+        Ok(status) if status.success() => TaskStatus::Success,
+        Ok(status) => TaskStatus::Failed(status.code().unwrap_or(-1)),
+        Err(_) => TaskStatus::Failed(-1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(hash: u64, cmd: &str, deps: &[u64]) -> Arc<TaskNode> {
+        Arc::new(TaskNode {
+            cmd: cmd.to_string(),
+            deps: deps.iter().copied().collect(),
+            hash,
+            alias: format!("root:task_{hash}"),
+            ..Default::default()
+        })
+    }
+
+    fn driver() -> Driver {
+        Driver::new(2, false, PathBuf::from("."))
+    }
+
+    #[test]
+    fn test_run_nodes_happy_path_dag() {
+        // 1 depends on 2, which depends on 3; all succeed.
+        let nodes: HashMap<u64, Arc<TaskNode>> = [
+            (1, node(1, "true", &[2])),
+            (2, node(2, "true", &[3])),
+            (3, node(3, "true", &[])),
+        ]
+        .into_iter()
+        .collect();
+
+        let report = driver().run_nodes(&nodes).expect("run_nodes failed");
+
+        assert!(report.success);
+        for hash in [1, 2, 3] {
+            assert_eq!(report.results[&hash].status, TaskStatus::Success);
+        }
+    }
+
+    #[test]
+    fn test_run_nodes_failed_dependency_skips_dependents() {
+        // 1 depends on 2 (fails), which depends on 3 (succeeds).
+        let nodes: HashMap<u64, Arc<TaskNode>> = [
+            (1, node(1, "true", &[2])),
+            (2, node(2, "false", &[3])),
+            (3, node(3, "true", &[])),
+        ]
+        .into_iter()
+        .collect();
+
+        let report = driver().run_nodes(&nodes).expect("run_nodes failed");
+
+        assert!(!report.success);
+        assert_eq!(report.results[&3].status, TaskStatus::Success);
+        assert!(matches!(report.results[&2].status, TaskStatus::Failed(_)));
+        assert_eq!(report.results[&1].status, TaskStatus::Skipped);
+    }
+}
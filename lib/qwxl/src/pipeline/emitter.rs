@@ -1,19 +1,63 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 
 use minijinja::Environment;
 use serde::Serialize;
 
-use crate::pipeline::{Pipeline, error::PipelineError, renderer::TaskNode};
+use crate::pipeline::{
+    Pipeline,
+    ast::ParamType,
+    error::PipelineError,
+    generator::Generator,
+    graph::{find_cycle, resolve_root_tasks, resolve_single_task, topological_order},
+    resolver::TaskNode,
+};
 
 pub const SCRIPT_TEMPLATE_NAME: &str = "script.sh.j2";
 const SCRIPT_TEMPLATE_SOURCE: &str = include_str!("templates/script.sh.j2");
 
+#[derive(Serialize)]
+struct TemplateParam {
+    /// Declared arg name, used as the `--name=value` CLI flag for root tasks.
+    name: String,
+    /// Shell-level validation regex applied to a CLI-supplied value before
+    /// it's bound; empty for types (string/path) that accept anything.
+    validate_regex: String,
+    /// Literal default substituted when the call site/CLI omits this arg.
+    default: String,
+}
+
 #[derive(Serialize)]
 struct TemplateTask {
     name: String,
     body: String,
     source: String,
+    /// Display names of this task's direct dependencies, so the generated
+    /// dispatcher knows which recorded PIDs to wait on before starting it.
+    deps: Vec<String>,
+    /// Hex-encoded `TaskNode.hash`, used as the stamp file's name.
+    hash_hex: String,
+    /// Composite stamp: hash(cmd + sorted dep stamps + content of declared inputs).
+    /// A cached run is reused only when this matches the previously written stamp.
+    stamp: String,
+    /// Glob patterns the task is expected to produce, checked for existence
+    /// before trusting a cached stamp.
+    outputs: Vec<String>,
+    /// Declared parameters, bound as positional shell arguments (`$1`, `$2`,
+    /// ...) in declaration order inside the task function body.
+    params: Vec<TemplateParam>,
+    /// Whether this is one of the dispatcher's entry points, i.e. a root
+    /// task whose `params` should also be exposed as `--name=value` CLI flags.
+    is_root: bool,
+}
+
+fn validate_regex_for(ty: ParamType) -> &'static str {
+    match ty {
+        ParamType::String | ParamType::Path => "",
+        ParamType::Int => r"^-?[0-9]+$",
+        ParamType::Bool => r"^(true|false)$",
+    }
 }
 
 /// A stateless generator configuration.
@@ -35,78 +79,198 @@ impl ShellGenerator {
     pub fn generate(&self, pipeline: &mut Pipeline) -> Result<String, PipelineError> {
         let root_alias = pipeline.config.root_alias.clone();
 
-        // 1. Identify Root Tasks (Entry Points)
-        let root_hash = pipeline
-            .stores
-            .aliases
-            .get(&root_alias)
-            .ok_or_else(|| PipelineError::Internal("Root alias not found".to_string()))?;
-
-        let root_task_names: Vec<String> = {
-            let meta = pipeline
-                .stores
-                .metamodules
-                .get(root_hash)
-                .ok_or_else(|| PipelineError::Internal("Root module not found".to_string()))?;
-            meta.module.tasks.keys().cloned().collect()
-        };
+        // 1-3. Resolve root tasks and collect the reachable subgraph (roots +
+        // transitive deps), shared with every other `Generator` backend.
+        let (root_task_names, nodes, display_names) = resolve_root_tasks(pipeline, &root_alias)?;
 
-        let mut tasks_to_render: Vec<TemplateTask> = Vec::new();
-        let mut visited_hashes: HashSet<u64> = HashSet::new();
-        let mut processing_queue: VecDeque<Arc<TaskNode>> = VecDeque::new();
+        render_script(&root_alias, pipeline.config.jobs.max(1), &root_task_names, &nodes, &display_names)
+    }
+}
 
-        // 2. Compile Root Tasks & Queue Dependencies
-        for task_name in &root_task_names {
-            // Compile the entry point
-            let node = pipeline.render(&root_alias, task_name)?;
+/// Renders `script.sh.j2` over an already-resolved subgraph: dependencies
+/// first in `deps`-topological order as shell functions, then a dispatcher
+/// exposing `root_task_names` as `--flag=value` CLI commands. Shared by
+/// `ShellGenerator::generate` (every root task on a module) and
+/// `Pipeline::emit_script` (one task plus its transitive deps).
+fn render_script(
+    root_alias: &str,
+    jobs: usize,
+    root_task_names: &[String],
+    nodes: &HashMap<u64, Arc<TaskNode>>,
+    display_names: &HashMap<u64, String>,
+) -> Result<String, PipelineError> {
+    // Kahn's algorithm over `deps`: every `task_<hash>` function must be defined
+    // before any caller references it, so dependencies are emitted before dependents.
+    let order = topological_order(nodes).ok_or_else(|| PipelineError::Cycle(find_cycle(nodes, display_names)))?;
 
-            // Mark as visited so we don't duplicate logic if it calls itself
-            if visited_hashes.insert(node.hash) {
-                let node_arc = Arc::new(node.clone());
+    // Every node needs a display name, not just the root tasks, so dependents
+    // can reference their dependencies' recorded PIDs by name.
+    let name_of = |hash: u64| {
+        display_names
+            .get(&hash)
+            .cloned()
+            .unwrap_or_else(|| format!("task_{:x}", hash))
+    };
 
-                tasks_to_render.push(TemplateTask {
-                    name: format!("{}:{}", root_alias, task_name),
-                    body: node.cmd.clone(),
-                    source: format!("{}.{}", root_alias, task_name),
-                });
+    // Stamps fold in each dependency's stamp, so they must be computed in the
+    // same dependency-first order used for emission.
+    let mut stamp_hashes: HashMap<u64, u64> = HashMap::new();
+    let mut tasks_to_render: Vec<TemplateTask> = Vec::with_capacity(order.len());
+    for hash in &order {
+        let hash = *hash;
+        let node = &nodes[&hash];
+        let stamp = compute_stamp(node, &stamp_hashes);
+        stamp_hashes.insert(hash, stamp);
 
-                processing_queue.push_back(node_arc);
-            }
-        }
+        let params: Vec<TemplateParam> = node
+            .args
+            .iter()
+            .map(|param| TemplateParam {
+                name: param.name.clone(),
+                validate_regex: validate_regex_for(param.ty).to_string(),
+                default: param.default.clone().unwrap_or_default(),
+            })
+            .collect();
 
-        // 3. Process Transitive Dependencies
-        while let Some(node) = processing_queue.pop_front() {
-            for dep_hash in &node.deps {
-                if visited_hashes.insert(*dep_hash) {
-                    if let Some(dep_node) = pipeline.stores.tasks.get(dep_hash) {
-                        tasks_to_render.push(TemplateTask {
-                            name: format!("task_{:x}", dep_hash),
-                            body: dep_node.cmd.clone(),
-                            source: format!("Hash: {:x}", dep_hash),
-                        });
-
-                        processing_queue.push_back(dep_node.clone());
-                    }
-                }
-            }
+        tasks_to_render.push(TemplateTask {
+            name: name_of(hash),
+            body: node.cmd.clone(),
+            source: format!("task_{:x}", hash),
+            deps: node.deps.iter().map(|dep| name_of(*dep)).collect(),
+            hash_hex: format!("{:x}", hash),
+            stamp: format!("{:x}", stamp),
+            outputs: node.outputs.clone(),
+            is_root: display_names.get(&hash).is_some(),
+            params,
+        });
+    }
+
+    // Every root command only pre-launches the dependencies it can actually
+    // reach, not the union of every other root's deps, so running one
+    // command never has the side effect of also running another's.
+    let name_to_hash: HashMap<&str, u64> =
+        display_names.iter().map(|(hash, name)| (name.as_str(), *hash)).collect();
+
+    // Root commands need their `fn_name` (the dispatcher's `run_task` target)
+    // alongside their declared params, so the CLI dispatcher can turn
+    // `--name=value` flags into positional args for that one task, plus the
+    // (topologically ordered) names of the non-root deps it needs launched
+    // ahead of itself.
+    let root_commands: Vec<serde_json::Value> = root_task_names
+        .iter()
+        .filter_map(|name| {
+            let fn_name = format!("{}:{}", root_alias, name);
+            let root_hash = *name_to_hash.get(fn_name.as_str())?;
+            let closure = reachable_deps(root_hash, nodes);
+            // Topologically ordered (since `order` already is), so each dep
+            // is safe to `run_task` before any task that needs it.
+            let deps: Vec<String> = order
+                .iter()
+                .filter(|hash| closure.contains(hash))
+                .map(|hash| name_of(*hash))
+                .collect();
+
+            tasks_to_render.iter().find(|t| t.name == fn_name).map(|t| {
+                serde_json::json!({
+                    "name": name,
+                    "fn_name": fn_name,
+                    "params": t.params,
+                    "deps": deps,
+                })
+            })
+        })
+        .collect();
+
+    let env = ShellGenerator::setup_env();
+    let template = env
+        .get_template(SCRIPT_TEMPLATE_NAME)
+        .map_err(|e| PipelineError::Internal(e.to_string()))?;
+
+    let context = serde_json::json!({
+        "tasks": tasks_to_render,
+        "commands": root_task_names,
+        "root_commands": root_commands,
+        "root_alias": root_alias,
+        "jobs": jobs,
+    });
+
+    template
+        .render(context)
+        .map_err(|e| PipelineError::Internal(e.to_string()))
+}
+
+impl Pipeline {
+    /// Resolves `task` on `alias` plus its full transitive dependency
+    /// closure and renders it through `script.sh.j2` into a single
+    /// self-contained shell script -- a portable export of one task (and
+    /// whatever it depends on) that runs standalone, without this tool
+    /// installed.
+    pub fn emit_script(&mut self, alias: &str, task: &str) -> Result<String, PipelineError> {
+        let jobs = self.config.jobs.max(1);
+        let (nodes, display_names) = resolve_single_task(self, alias, task)?;
+        let root_task_names = vec![task.to_string()];
+
+        render_script(alias, jobs, &root_task_names, &nodes, &display_names)
+    }
+}
+
+impl Generator for ShellGenerator {
+    fn generate(&self, pipeline: &mut Pipeline) -> Result<String, PipelineError> {
+        ShellGenerator::generate(self, pipeline)
+    }
+}
+
+/// Walks `node.deps` from `root_hash` and returns every hash reachable from
+/// it, `root_hash` itself excluded -- the subset of `nodes` that root
+/// command actually needs pre-launched, as opposed to every other root's deps.
+fn reachable_deps(root_hash: u64, nodes: &HashMap<u64, Arc<TaskNode>>) -> HashSet<u64> {
+    let mut seen: HashSet<u64> = HashSet::new();
+    let mut queue: Vec<u64> = nodes.get(&root_hash).map(|node| node.deps.iter().copied().collect()).unwrap_or_default();
+
+    while let Some(hash) = queue.pop() {
+        if !seen.insert(hash) {
+            continue;
+        }
+        if let Some(node) = nodes.get(&hash) {
+            queue.extend(node.deps.iter().copied());
         }
+    }
 
-        // 4. Render Template
-        let env = Self::setup_env();
-        let template = env
-            .get_template(SCRIPT_TEMPLATE_NAME)
-            .map_err(|e| PipelineError::Internal(e.to_string()))?;
+    seen
+}
 
-        let context = serde_json::json!({
-            "tasks": tasks_to_render,
-            "commands": root_task_names,
-            "root_alias": root_alias,
-        });
+/// Computes a task's composite stamp: `hash(cmd + sorted dep stamps + content
+/// hashes of declared input files)`. Because a stamp folds in its dependencies'
+/// stamps, changing any upstream command invalidates everything downstream.
+fn compute_stamp(node: &TaskNode, stamp_hashes: &HashMap<u64, u64>) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    node.cmd.hash(&mut hasher);
 
-        template
-            .render(context)
-            .map_err(|e| PipelineError::Internal(e.to_string()))
+    let mut dep_stamps: Vec<u64> = node
+        .deps
+        .iter()
+        .filter_map(|dep| stamp_hashes.get(dep).copied())
+        .collect();
+    dep_stamps.sort_unstable();
+    dep_stamps.hash(&mut hasher);
+
+    for pattern in &node.inputs {
+        let mut matches: Vec<std::path::PathBuf> = glob::glob(pattern)
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .collect();
+        matches.sort();
+
+        for path in matches {
+            path.to_string_lossy().hash(&mut hasher);
+            if let Ok(content) = std::fs::read(&path) {
+                content.hash(&mut hasher);
+            }
+        }
     }
+
+    hasher.finish()
 }
 
 #[cfg(test)]
@@ -122,7 +286,11 @@ mod tests {
     }
 
     fn register_module(p: &mut Pipeline, alias: &str, module: Module, hash: u64) {
-        let meta = MetaModule { module, hash };
+        let meta = MetaModule {
+            module,
+            hash,
+            ..Default::default()
+        };
         p.stores.metamodules.insert(hash, meta);
         p.stores.aliases.insert(alias.to_string(), hash);
     }
@@ -145,7 +313,35 @@ mod tests {
 
         assert!(script.contains("root:build() {"));
         assert!(script.contains("cargo build"));
-        assert!(script.contains("FN=\"root:$CMD\""));
+        assert!(script.contains("FN=\"root:build\""));
+    }
+
+    #[test]
+    fn test_generate_with_typed_arg() {
+        let mut p = create_pipeline();
+        let mut module = Module::default();
+        module.tasks.insert(
+            "build".to_string(),
+            Task {
+                cmd: "cargo build --profile={{ props.profile }}".to_string(),
+                args: vec![crate::pipeline::ast::Param {
+                    name: "profile".to_string(),
+                    ty: crate::pipeline::ast::ParamType::String,
+                    default: Some("debug".to_string()),
+                    required: false,
+                }],
+                ..Default::default()
+            },
+        );
+        register_module(&mut p, "root", module, 2);
+
+        let generator = ShellGenerator::new();
+        let script = generator.generate(&mut p).expect("Generate failed");
+
+        // Declared params are bound positionally inside the task function...
+        assert!(script.contains("local profile=\"${1:-debug}\""));
+        // ...and surfaced as a CLI flag in the dispatcher.
+        assert!(script.contains("--profile=*)"));
     }
 
     #[test]
@@ -175,12 +371,53 @@ mod tests {
         );
         register_module(&mut p, "root", root, 20);
 
+        let helper_hash = p.resolve_task("lib", "helper").expect("resolve helper").hash;
+        let helper_fn = format!("task_{:x}", helper_hash);
+
         let generator = ShellGenerator::new();
         let script = generator.generate(&mut p).expect("Generate failed");
 
+        // Two distinct functions are emitted -- one per node. `main`'s body
+        // holds `helper`'s rendered command inline (a `tasks.x()` call
+        // renders to the callee's command), and `helper` also gets its own
+        // function, so the command appears once in each.
         assert!(script.contains("root:main() {"));
-        // Dependency should be rendered as hash task
-        assert!(script.contains("task_"));
-        assert!(script.contains("echo help"));
+        assert!(script.contains(&format!("{}() {{", helper_fn)));
+        assert_eq!(script.matches("echo help").count(), 2);
+
+        // `main`'s recorded deps include `helper`'s hash, so the dispatcher
+        // also waits on it as a separate task; the inlining above does not
+        // lose the dependency edge.
+        assert!(script.contains(&format!("TASK_DEPS[\"root:main\"]=\"{}\"", helper_fn)));
+    }
+
+    #[test]
+    fn test_emit_script_scopes_to_one_task() {
+        let mut p = create_pipeline();
+
+        let mut module = Module::default();
+        module.tasks.insert(
+            "build".to_string(),
+            Task {
+                cmd: "cargo build".to_string(),
+                ..Default::default()
+            },
+        );
+        module.tasks.insert(
+            "unrelated".to_string(),
+            Task {
+                cmd: "echo unrelated".to_string(),
+                ..Default::default()
+            },
+        );
+        register_module(&mut p, "root", module, 3);
+
+        let script = p.emit_script("root", "build").expect("emit_script failed");
+
+        assert!(script.contains("root:build() {"));
+        assert!(script.contains("cargo build"));
+        assert!(script.contains("FN=\"root:build\""));
+        // Only the requested task is exported, not every root task.
+        assert!(!script.contains("unrelated"));
     }
 }
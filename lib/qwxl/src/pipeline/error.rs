@@ -13,6 +13,9 @@ pub enum PipelineError {
     #[error("Cyclic dependency detected for alias: {0:?}")]
     CyclicDependency(IHashSet<String>),
 
+    #[error("Cyclic task dependency detected: {0}")]
+    Cycle(String),
+
     #[error("Invalid alias format: {0}")]
     InvalidAliasFormat(String),
 
@@ -28,9 +31,21 @@ pub enum PipelineError {
     #[error("Task not found: {0}")]
     TaskNotFound(String),
 
+    #[error("Task `{0}` argument `{1}` does not match its declared type")]
+    InvalidArgument(String, String),
+
+    #[error("Task `{0}` is missing required argument `{1}`")]
+    InvalidArgRef(String, String),
+
     #[error("Unknown file format: {0}")]
     UnsupportedFormat(String),
 
+    #[error("qwex.lock is out of date for: {0} (run without --frozen to re-pin it)")]
+    LockfileMismatch(String),
+
+    #[error("Sandbox setup failed at '{0}': {1}")]
+    SandboxSetup(String, String),
+
     #[error(transparent)]
     SerdeYaml(#[from] serde_saphyr::Error),
 
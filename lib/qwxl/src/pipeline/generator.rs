@@ -0,0 +1,8 @@
+use crate::pipeline::{Pipeline, error::PipelineError};
+
+/// A pluggable output backend: given a resolved pipeline, renders the full
+/// task graph into whatever text format the backend targets (a shell script,
+/// a Makefile, ...). Selected via `Config.target_format`.
+pub trait Generator {
+    fn generate(&self, pipeline: &mut Pipeline) -> Result<String, PipelineError>;
+}
@@ -0,0 +1,212 @@
+//! Dependency-graph utilities shared by every `Generator` backend: resolving
+//! a root module's tasks into the full reachable `TaskNode` subgraph, and
+//! ordering/validating that subgraph via Kahn's algorithm.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use crate::pipeline::{Pipeline, error::PipelineError, resolver::TaskNode};
+
+/// Resolves every task on `alias`'s root module and walks `TaskNode.deps` to
+/// collect the full reachable subgraph (roots + transitive dependencies).
+/// Returns the root task names (in module order), the subgraph keyed by task
+/// hash, and a display name for every root task keyed by its hash.
+pub fn resolve_root_tasks(
+    pipeline: &mut Pipeline,
+    alias: &str,
+) -> Result<(Vec<String>, HashMap<u64, Arc<TaskNode>>, HashMap<u64, String>), PipelineError> {
+    let root_hash = pipeline
+        .stores
+        .aliases
+        .get(alias)
+        .ok_or_else(|| PipelineError::Internal("Root alias not found".to_string()))?;
+
+    let root_task_names: Vec<String> = {
+        let meta = pipeline
+            .stores
+            .metamodules
+            .get(root_hash)
+            .ok_or_else(|| PipelineError::Internal("Root module not found".to_string()))?;
+        meta.module.tasks.keys().cloned().collect()
+    };
+
+    // Compiling every root task resolves the full transitive closure of
+    // `TaskNode`s into `pipeline.stores.tasks`, keyed by their content hash.
+    let mut display_names: HashMap<u64, String> = HashMap::new();
+    let mut root_hashes: Vec<u64> = Vec::new();
+    for task_name in &root_task_names {
+        let node = pipeline.resolve_task(alias, task_name)?;
+        display_names.insert(node.hash, format!("{}:{}", alias, task_name));
+        root_hashes.push(node.hash);
+    }
+
+    let mut nodes: HashMap<u64, Arc<TaskNode>> = HashMap::new();
+    let mut discovery_queue: VecDeque<u64> = root_hashes.iter().copied().collect();
+    while let Some(hash) = discovery_queue.pop_front() {
+        if nodes.contains_key(&hash) {
+            continue;
+        }
+        let node = pipeline.stores.tasks.get(&hash).ok_or_else(|| {
+            PipelineError::Internal(format!("Task {:x} missing from store", hash))
+        })?;
+        for dep_hash in &node.deps {
+            discovery_queue.push_back(*dep_hash);
+        }
+        nodes.insert(hash, node.clone());
+    }
+
+    Ok((root_task_names, nodes, display_names))
+}
+
+/// Single-entry-point counterpart to `resolve_root_tasks`: resolves just
+/// `task` on `alias`'s module and walks `TaskNode.deps` to collect its
+/// reachable subgraph, for exporting one task (plus whatever it depends on)
+/// as a standalone script rather than the whole module.
+pub fn resolve_single_task(
+    pipeline: &mut Pipeline,
+    alias: &str,
+    task: &str,
+) -> Result<(HashMap<u64, Arc<TaskNode>>, HashMap<u64, String>), PipelineError> {
+    let node = pipeline.resolve_task(alias, task)?;
+
+    let mut display_names: HashMap<u64, String> = HashMap::new();
+    display_names.insert(node.hash, format!("{}:{}", alias, task));
+
+    let mut nodes: HashMap<u64, Arc<TaskNode>> = HashMap::new();
+    let mut discovery_queue: VecDeque<u64> = VecDeque::from([node.hash]);
+    while let Some(hash) = discovery_queue.pop_front() {
+        if nodes.contains_key(&hash) {
+            continue;
+        }
+        let node = pipeline.stores.tasks.get(&hash).ok_or_else(|| {
+            PipelineError::Internal(format!("Task {:x} missing from store", hash))
+        })?;
+        for dep_hash in &node.deps {
+            discovery_queue.push_back(*dep_hash);
+        }
+        nodes.insert(hash, node.clone());
+    }
+
+    Ok((nodes, display_names))
+}
+
+/// Returns `names[hash]` if known, otherwise the `task_<hash>` fallback used
+/// for dependency nodes that aren't root tasks.
+pub fn display_name(hash: u64, names: &HashMap<u64, String>) -> String {
+    names
+        .get(&hash)
+        .cloned()
+        .unwrap_or_else(|| format!("task_{:x}", hash))
+}
+
+/// Runs Kahn's algorithm over `node.deps` and returns a dependency-first order
+/// (every dependency appears before anything that depends on it). Returns `None`
+/// if the graph has a cycle, i.e. fewer nodes were emitted than exist.
+pub fn topological_order(nodes: &HashMap<u64, Arc<TaskNode>>) -> Option<Vec<u64>> {
+    let mut in_degree: HashMap<u64, usize> = nodes.keys().map(|h| (*h, 0)).collect();
+    let mut dependents: HashMap<u64, Vec<u64>> = HashMap::new();
+
+    for (hash, node) in nodes {
+        for dep_hash in &node.deps {
+            *in_degree.get_mut(hash).unwrap() += 1;
+            dependents.entry(*dep_hash).or_default().push(*hash);
+        }
+    }
+
+    // Seed with zero-in-degree nodes, sorted for a stable, reproducible order.
+    let mut frontier: Vec<u64> = in_degree
+        .iter()
+        .filter(|(_, deg)| **deg == 0)
+        .map(|(h, _)| *h)
+        .collect();
+    frontier.sort_unstable();
+    let mut queue: VecDeque<u64> = frontier.into();
+
+    let mut order: Vec<u64> = Vec::with_capacity(nodes.len());
+    while let Some(hash) = queue.pop_front() {
+        order.push(hash);
+
+        if let Some(successors) = dependents.get(&hash) {
+            let mut ready: Vec<u64> = Vec::new();
+            for successor in successors {
+                let deg = in_degree.get_mut(successor).unwrap();
+                *deg -= 1;
+                if *deg == 0 {
+                    ready.push(*successor);
+                }
+            }
+            ready.sort_unstable();
+            queue.extend(ready);
+        }
+    }
+
+    if order.len() == nodes.len() {
+        Some(order)
+    } else {
+        None
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Color {
+    White,
+    Gray,
+    Black,
+}
+
+/// Finds a back edge via DFS with white/gray/black coloring and renders the
+/// offending path, e.g. `root:main -> lib:helper -> root:main`.
+pub fn find_cycle(nodes: &HashMap<u64, Arc<TaskNode>>, names: &HashMap<u64, String>) -> String {
+    fn visit(
+        hash: u64,
+        nodes: &HashMap<u64, Arc<TaskNode>>,
+        names: &HashMap<u64, String>,
+        color: &mut HashMap<u64, Color>,
+        stack: &mut Vec<u64>,
+    ) -> Option<String> {
+        color.insert(hash, Color::Gray);
+        stack.push(hash);
+
+        if let Some(node) = nodes.get(&hash) {
+            let mut dep_hashes: Vec<u64> = node.deps.iter().copied().collect();
+            dep_hashes.sort_unstable();
+
+            for dep_hash in dep_hashes {
+                match color.get(&dep_hash).copied().unwrap_or(Color::White) {
+                    Color::White => {
+                        if let Some(path) = visit(dep_hash, nodes, names, color, stack) {
+                            return Some(path);
+                        }
+                    }
+                    Color::Gray => {
+                        let start = stack.iter().position(|h| *h == dep_hash).unwrap_or(0);
+                        let mut path: Vec<String> =
+                            stack[start..].iter().map(|h| display_name(*h, names)).collect();
+                        path.push(display_name(dep_hash, names));
+                        return Some(path.join(" -> "));
+                    }
+                    Color::Black => {}
+                }
+            }
+        }
+
+        stack.pop();
+        color.insert(hash, Color::Black);
+        None
+    }
+
+    let mut color: HashMap<u64, Color> = nodes.keys().map(|h| (*h, Color::White)).collect();
+    let mut roots: Vec<u64> = nodes.keys().copied().collect();
+    roots.sort_unstable();
+
+    for hash in roots {
+        if color.get(&hash).copied().unwrap_or(Color::White) == Color::White {
+            let mut stack = Vec::new();
+            if let Some(path) = visit(hash, nodes, names, &mut color, &mut stack) {
+                return path;
+            }
+        }
+    }
+
+    "<cycle involving unreachable tasks>".to_string()
+}
@@ -0,0 +1,186 @@
+//! `qwex.lock` pins, for every import resolved while parsing the source
+//! graph, the `str_hash` its content had at that time -- so a dependency
+//! file changing underneath a build is caught instead of silently altering
+//! the compiled graph. Same RON-on-disk convention as the artifact cache in
+//! `pipeline.rs`, just keyed by import path instead of by root content hash.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::pipeline::{Pipeline, error::PipelineError};
+
+/// Resolved import paths mapped to the content hash they had when last
+/// pinned. A `BTreeMap` keeps the on-disk file in a stable, diffable order.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub imports: BTreeMap<String, u64>,
+}
+
+impl Lockfile {
+    /// Reads `path`, returning `None` when no lockfile has been written yet
+    /// rather than treating an absent `qwex.lock` as a mismatch.
+    fn read(path: &Path) -> Result<Option<Self>, PipelineError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let raw = std::fs::read_to_string(path)?;
+        Ok(Some(ron::de::from_str(&raw)?))
+    }
+
+    fn write(&self, path: &Path) -> Result<(), PipelineError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let raw = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, raw)?;
+        Ok(())
+    }
+}
+
+impl Pipeline {
+    /// `qwex.lock` lives next to the source file, mirroring how `Cargo.lock`
+    /// sits beside `Cargo.toml` rather than under a build-output directory.
+    fn lockfile_path(&self) -> PathBuf {
+        self.config
+            .get_source_path()
+            .parent()
+            .map(|dir| dir.join("qwex.lock"))
+            .unwrap_or_else(|| self.config.get_home_dir().join("qwex.lock"))
+    }
+
+    /// Snapshots the content hash of every module resolved so far (root plus
+    /// every transitively resolved `uses` import), keyed by its original
+    /// `uses:` spec for a remote import (portable across machines) or its
+    /// on-disk path otherwise.
+    fn current_lockfile(&self) -> Lockfile {
+        let imports = self
+            .stores
+            .metamodules
+            .iter()
+            .map(|(hash, meta)| {
+                let key = meta
+                    .origin
+                    .clone()
+                    .unwrap_or_else(|| meta.path_buf.to_string_lossy().into_owned());
+                (key, *hash)
+            })
+            .collect();
+        Lockfile { imports }
+    }
+
+    /// Compares the just-parsed import graph against any existing
+    /// `qwex.lock`. A diverging entry is a warning by default so a legitimate
+    /// dependency bump doesn't block a build; `Config.frozen` (the CLI's
+    /// `--frozen`/`--locked` flag) promotes it to a hard error for CI.
+    pub(crate) fn check_lockfile(&mut self) -> Result<(), PipelineError> {
+        let Some(locked) = Lockfile::read(&self.lockfile_path())? else {
+            return Ok(());
+        };
+
+        let current = self.current_lockfile();
+        let diverged: Vec<&String> = locked
+            .imports
+            .iter()
+            .filter(|(import, hash)| current.imports.get(*import) != Some(*hash))
+            .map(|(import, _)| import)
+            .collect();
+
+        if diverged.is_empty() {
+            return Ok(());
+        }
+
+        if self.config.frozen {
+            return Err(PipelineError::LockfileMismatch(
+                diverged
+                    .iter()
+                    .map(|s| s.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ));
+        }
+
+        for import in diverged {
+            tracing::warn!(
+                "qwex.lock is out of date for '{}': re-pinning it",
+                import
+            );
+        }
+        Ok(())
+    }
+
+    /// Rewrites `qwex.lock` from the freshly parsed import graph. Skipped
+    /// entirely under `Config.frozen`, so a CI run can rely on the lockfile
+    /// being read-only.
+    pub(crate) fn write_lockfile(&self) -> Result<(), PipelineError> {
+        self.current_lockfile().write(&self.lockfile_path())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{
+        Config,
+        ast::{MetaModule, Module},
+    };
+
+    /// A scratch dir qwex.lock/qwex.yaml can live next to, unique per test so
+    /// parallel `cargo test` runs don't trample each other.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("qwex_lockfile_test_{}_{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create scratch dir");
+        dir
+    }
+
+    fn pipeline_with_source(dir: &Path, frozen: bool) -> Pipeline {
+        let config = Config {
+            source_path: dir.join("qwex.yaml"),
+            frozen,
+            ..Default::default()
+        };
+        Pipeline::new(config)
+    }
+
+    fn register_import(p: &mut Pipeline, path: &Path, hash: u64) {
+        let meta = MetaModule {
+            module: Module::default(),
+            hash,
+            path_buf: path.to_path_buf(),
+            ..Default::default()
+        };
+        p.stores.metamodules.insert(hash, meta);
+    }
+
+    #[test]
+    fn test_check_lockfile_passes_when_unchanged() {
+        let dir = scratch_dir("unchanged");
+        let lib_path = dir.join("lib.yaml");
+
+        let mut p = pipeline_with_source(&dir, false);
+        register_import(&mut p, &lib_path, 42);
+        p.write_lockfile().expect("write_lockfile failed");
+
+        p.check_lockfile().expect("unchanged lockfile should pass");
+    }
+
+    #[test]
+    fn test_check_lockfile_frozen_errors_on_divergence() {
+        let dir = scratch_dir("divergence");
+        let lib_path = dir.join("lib.yaml");
+
+        // Pin the lockfile against the original content hash...
+        let mut pinned = pipeline_with_source(&dir, false);
+        register_import(&mut pinned, &lib_path, 42);
+        pinned.write_lockfile().expect("write_lockfile failed");
+
+        // ...then simulate the import having changed underneath the build.
+        let mut p = pipeline_with_source(&dir, true);
+        register_import(&mut p, &lib_path, 99);
+
+        let err = p.check_lockfile().expect_err("changed import should be caught");
+        assert!(matches!(err, PipelineError::LockfileMismatch(ref msg) if msg.contains(&lib_path.to_string_lossy().into_owned())));
+    }
+}
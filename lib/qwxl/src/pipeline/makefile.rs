@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use crate::pipeline::{
+    Pipeline,
+    error::PipelineError,
+    generator::Generator,
+    graph::{display_name, find_cycle, resolve_root_tasks, topological_order},
+};
+
+/// Renders the resolved task graph as a `Makefile`: every task becomes a
+/// `.PHONY` rule (or a file rule when it declares `outputs:`) whose
+/// prerequisites are its dependencies' rule names, reusing the same
+/// dependency graph the shell generator walks.
+pub struct MakefileGenerator;
+
+impl MakefileGenerator {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn generate(&self, pipeline: &mut Pipeline) -> Result<String, PipelineError> {
+        let root_alias = pipeline.config.root_alias.clone();
+
+        let (root_task_names, nodes, display_names) = resolve_root_tasks(pipeline, &root_alias)?;
+
+        let order = topological_order(&nodes)
+            .ok_or_else(|| PipelineError::Cycle(find_cycle(&nodes, &display_names)))?;
+
+        let name_of = |hash: u64| display_name(hash, &display_names);
+
+        // Root task names are unique within a module, so inverting the hash ->
+        // display-name map recovers each root's hash without re-walking `nodes`.
+        let root_hash_of: HashMap<String, u64> = display_names
+            .iter()
+            .map(|(hash, display)| (display.clone(), *hash))
+            .filter_map(|(display, hash)| {
+                display
+                    .strip_prefix(&format!("{}:", root_alias))
+                    .map(|name| (name.to_string(), hash))
+            })
+            .collect();
+
+        let mut out = String::new();
+        out.push_str(".PHONY: ");
+        out.push_str(&root_task_names.join(" "));
+        out.push_str("\n\n");
+        for task_name in &root_task_names {
+            if let Some(hash) = root_hash_of.get(task_name) {
+                out.push_str(task_name);
+                out.push_str(": ");
+                out.push_str(&name_of(*hash));
+                out.push('\n');
+            }
+        }
+        out.push('\n');
+
+        for hash in order {
+            let node = &nodes[&hash];
+            let name = name_of(hash);
+
+            let target = if node.outputs.is_empty() {
+                out.push_str(".PHONY: ");
+                out.push_str(&name);
+                out.push('\n');
+                name.clone()
+            } else {
+                node.outputs.join(" ")
+            };
+
+            out.push_str(&target);
+            out.push(':');
+            for dep_hash in &node.deps {
+                out.push(' ');
+                out.push_str(&name_of(*dep_hash));
+            }
+            out.push('\n');
+            out.push('\t');
+            out.push_str(&node.cmd.replace('\n', "\n\t"));
+            out.push('\n');
+            if !node.outputs.is_empty() && target != name {
+                // Also expose a phony alias so `make <name>` works even when the
+                // rule's real target is a file.
+                out.push_str(&name);
+                out.push_str(": ");
+                out.push_str(&target);
+                out.push('\n');
+            }
+            out.push('\n');
+        }
+
+        Ok(out)
+    }
+}
+
+impl Generator for MakefileGenerator {
+    fn generate(&self, pipeline: &mut Pipeline) -> Result<String, PipelineError> {
+        MakefileGenerator::generate(self, pipeline)
+    }
+}
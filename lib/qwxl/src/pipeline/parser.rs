@@ -1,5 +1,5 @@
-use ahash::{HashSet, RandomState};
-use std::hash::{BuildHasher, Hasher};
+use ahash::HashSet;
+use sha2::{Digest, Sha256};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
@@ -7,14 +7,42 @@ use crate::pipeline::ast::{IHashMap, MetaModule, UseRef};
 use crate::pipeline::{
     Pipeline,
     ast::{Module, PROP_PREFIX, TASK_PREFIX},
+    cache::{read_disk_entry, write_disk_entry},
     error::PipelineError,
+    remote,
 };
 
 // --- Hashing Utility ---
+// A SHA-256 digest of `t`, used wherever content identity must be stable
+// across processes and machines (the on-disk caches and `qwex.lock`, via
+// `str_hash`), unlike the process-seeded `ahash` hashers used elsewhere for
+// transient in-memory maps.
+pub fn str_digest(t: &str) -> [u8; 32] {
+    Sha256::digest(t.as_bytes()).into()
+}
+
+pub fn str_digest_hex(t: &str) -> String {
+    hex_encode(&str_digest(t))
+}
+
+/// Truncates a digest to a `u64` by reading its first 8 bytes big-endian.
+/// Store keys and `qwex.lock` entries use this `u64`; `str_digest_hex`
+/// gives the full digest (stored as `MetaModule.digest`).
+pub fn truncate_digest(digest: &[u8; 32]) -> u64 {
+    u64::from_be_bytes(digest[..8].try_into().unwrap())
+}
+
 pub fn str_hash(t: &str) -> u64 {
-    let mut h = RandomState::with_seed(0).build_hasher();
-    h.write(t.as_bytes());
-    h.finish()
+    truncate_digest(&str_digest(t))
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        write!(out, "{:02x}", byte).unwrap();
+    }
+    out
 }
 
 // --- Loaders ---
@@ -73,6 +101,7 @@ pub fn merge_features(mf: Module, is_src: bool, features: String) -> Module {
         uses: mf.uses.clone(),
         props: mf.props.clone(),
         tasks: mf.tasks.clone(),
+        aliases: mf.aliases.clone(),
         modules: IHashMap::default(),
     };
 
@@ -123,6 +152,10 @@ pub struct ModuleJob {
     pub path: PathBuf,
     pub alias: Option<String>,
     pub parent_alias: Option<String>,
+    /// The raw `uses:` spec this job was resolved from, when it named a
+    /// remote import. Carried through to `MetaModule.origin` so the
+    /// lockfile can pin the portable spec instead of the local cache path.
+    pub origin: Option<String>,
 }
 
 impl Pipeline {
@@ -130,6 +163,9 @@ impl Pipeline {
         if import.starts_with("@std/") {
             return Ok(PathBuf::from(import));
         }
+        if remote::is_remote(import) {
+            return remote::fetch(import, &self.config.get_home_dir());
+        }
         let parent_dir = parent.parent().ok_or_else(|| {
             std::io::Error::new(std::io::ErrorKind::NotFound, "Parent path has no directory")
         })?;
@@ -148,6 +184,7 @@ impl Pipeline {
             path,
             alias: Some(self.config.root_alias.clone()),
             parent_alias: None,
+            origin: None,
         };
         self.parse_one(job)
     }
@@ -164,6 +201,20 @@ impl Pipeline {
             return Ok(module.clone());
         }
 
+        let metamodule_cache_dir = self.config.get_home_dir().join("cache").join("metamodules");
+        if let Some(mut cached) =
+            read_disk_entry::<u64, MetaModule>(&content_hash, &metamodule_cache_dir)
+        {
+            // `hash` and `path_buf` are not serialized; restore them.
+            cached.hash = content_hash;
+            cached.path_buf = job.path.clone();
+            let arc = Arc::new(cached);
+            self.stores
+                .metamodules
+                .insert_as_arc(content_hash, arc.clone());
+            return Ok(arc);
+        }
+
         let path_str = job.path.to_string_lossy();
         let ext = if path_str.starts_with("@std") {
             "yaml"
@@ -198,6 +249,7 @@ impl Pipeline {
                     path: dep_path,
                     alias: None,
                     parent_alias: job.alias.clone(),
+                    origin: remote::is_remote(rel_path).then(|| rel_path.clone()),
                 })?;
                 *use_ref = UseRef::Hash(dep_module.hash);
             }
@@ -206,8 +258,12 @@ impl Pipeline {
         let metamodule = MetaModule {
             module,
             hash: content_hash,
+            digest: str_digest_hex(&content_arc),
             path_buf: job.path.clone(),
+            origin: job.origin.clone(),
         };
+        write_disk_entry(&content_hash, &metamodule, &metamodule_cache_dir);
+
         let arc = Arc::new(metamodule);
         self.stores
             .metamodules
@@ -0,0 +1,100 @@
+//! Remote `uses:` imports: `git+<repo-url>[#<rev>]`, or a bare `http(s)://`
+//! URL pointing directly at a module file. Fetched content is written under
+//! `<home_dir>/remote`: a URL's body under its own content hash, a git repo
+//! under the hash of its URL. Every build contacts the remote again: a URL
+//! is downloaded anew and an existing clone is re-fetched.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::pipeline::{error::PipelineError, parser::str_hash};
+
+/// Whether `import` names a remote source rather than a local relative path
+/// or an `@std/` builtin.
+pub fn is_remote(import: &str) -> bool {
+    import.starts_with("git+") || import.starts_with("http://") || import.starts_with("https://")
+}
+
+/// Fetches `import` into `<home_dir>/remote`, returning the local path to
+/// the resolved module file. The fetched content's own `str_hash` (computed
+/// right after, by the same `parse_one` code path as any local file) is
+/// what actually gets pinned into `qwex.lock` -- a retagged git ref or an
+/// edited URL target is caught the same way a changed local file would be.
+pub fn fetch(import: &str, home_dir: &Path) -> Result<PathBuf, PipelineError> {
+    let cache_dir = home_dir.join("remote");
+    std::fs::create_dir_all(&cache_dir)?;
+
+    match import.strip_prefix("git+") {
+        Some(spec) => fetch_git(spec, &cache_dir),
+        None => fetch_http(import, &cache_dir),
+    }
+}
+
+/// Bare URL case: the response body is cached under its own content hash.
+fn fetch_http(url: &str, cache_dir: &Path) -> Result<PathBuf, PipelineError> {
+    let body = ureq::get(url)
+        .call()
+        .and_then(|resp| resp.into_string().map_err(Into::into))
+        .map_err(|e| PipelineError::Internal(format!("Failed to fetch '{}': {}", url, e)))?;
+
+    let cached_path = cache_dir.join(format!("{:x}.yaml", str_hash(&body)));
+    if !cached_path.exists() {
+        std::fs::write(&cached_path, &body)?;
+    }
+    Ok(cached_path)
+}
+
+/// `git+<repo-url>[#<rev>]`: a shallow clone of `repo-url` at `rev`
+/// (defaulting to the remote's default branch). On later builds the existing
+/// clone is reused and re-fetched in place at `rev` (or `HEAD`) every time,
+/// whether or not `rev` changed. Resolves to that repo's root
+/// `qwex.yaml`.
+fn fetch_git(spec: &str, cache_dir: &Path) -> Result<PathBuf, PipelineError> {
+    let (repo_url, rev) = match spec.split_once('#') {
+        Some((url, rev)) => (url, Some(rev)),
+        None => (spec, None),
+    };
+    let repo_dir = cache_dir.join(format!("{:x}", str_hash(repo_url)));
+
+    if repo_dir.exists() {
+        run_git(
+            Command::new("git")
+                .arg("-C")
+                .arg(&repo_dir)
+                .arg("fetch")
+                .arg("--depth=1")
+                .arg("origin")
+                .arg(rev.unwrap_or("HEAD")),
+            repo_url,
+        )?;
+        run_git(
+            Command::new("git")
+                .arg("-C")
+                .arg(&repo_dir)
+                .arg("checkout")
+                .arg("FETCH_HEAD"),
+            repo_url,
+        )?;
+    } else {
+        let mut clone = Command::new("git");
+        clone.arg("clone").arg("--depth=1");
+        if let Some(rev) = rev {
+            clone.arg("--branch").arg(rev);
+        }
+        clone.arg(repo_url).arg(&repo_dir);
+        run_git(&mut clone, repo_url)?;
+    }
+
+    Ok(repo_dir.join("qwex.yaml"))
+}
+
+fn run_git(cmd: &mut Command, repo_url: &str) -> Result<(), PipelineError> {
+    let status = cmd.status()?;
+    if !status.success() {
+        return Err(PipelineError::Internal(format!(
+            "git command failed while fetching '{}'",
+            repo_url
+        )));
+    }
+    Ok(())
+}
@@ -1,11 +1,12 @@
+use std::collections::VecDeque;
 use std::sync::{Arc, Mutex, RwLock};
 
 use ahash::{HashMap, HashSet, HashSetExt as _};
-use minijinja::{Value, context, value::Object};
-use serde::Serialize;
+use minijinja::{Value, value::Object};
+use serde::{Deserialize, Serialize};
 
 use crate::pipeline::{
-    PipelineStore,
+    Pipeline, PipelineStore,
     ast::{IHashMap, IHashSet, Module, UseRef},
     error::PipelineError,
 };
@@ -13,21 +14,19 @@ use crate::pipeline::{
 pub const SCRIPT_TEMPLATE_NAME: &str = "script.sh.j2";
 pub const SCRIPT_TEMPLATE_SOURCE: &str = include_str!("templates/script.sh.j2");
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Resource {
-    Task { 
-        cmd: String, 
-        props: IHashMap<String, Value>, 
+    Task {
+        cmd: String,
+        props: IHashMap<String, Value>,
         rendered: String,
-
-    
-    
-     },
-    Prop { value: Value },
+    },
+    Prop {
+        value: Value,
+    },
 }
 
-
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum ResourceType {
     Props,
     Tasks,
@@ -44,7 +43,7 @@ impl ToString for ResourceType {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct RenderTarget {
     hash: u64,
     resource: ResourceType,
@@ -57,30 +56,69 @@ impl ToString for RenderTarget {
     }
 }
 
-#[derive(Debug, Serialize)]
-struct DependencyCollector {
-    pub target: RenderTarget,
-    pub store: Arc<PipelineStore>,
-    pub hard_deps: Arc<RwLock<HashMap<RenderTarget, HashSet<RenderTarget>>>>,
-    pub soft_deps: Arc<RwLock<HashMap<RenderTarget, HashSet<RenderTarget>>>>,
+/// Resolves a single `(module_hash, task_name)` identity's own namespace
+/// (`"props"`, `"tasks"`, or a directly-nested submodule name) into the
+/// `DependencyCollector` scoped to that namespace. Mirrors `resolver::RootContext`,
+/// but over `RenderTarget`s instead of `TaskNode`s.
+#[derive(Debug, Clone)]
+struct RenderContext {
+    /// The target whose body is currently being rendered; threaded through
+    /// so every lookup it makes records its edge against *this* identity
+    /// rather than against the shared `"props"`/`"tasks"` namespace prefix.
+    origin: RenderTarget,
+    /// The owning module, i.e. `store.metamodules[origin.hash].module`,
+    /// consulted for its own `props`/`tasks`/`modules` regardless of which
+    /// `ResourceType` `origin` itself is -- a submodule only ever contributes
+    /// its `tasks` (see `check_is_valid_target`), never its own nested scope.
+    module: Arc<Module>,
+    store: Arc<PipelineStore>,
+    resolved: Arc<Mutex<HashMap<RenderTarget, Arc<Resource>>>>,
+    hard_deps: Arc<RwLock<HashMap<RenderTarget, HashSet<RenderTarget>>>>,
+    soft_deps: Arc<RwLock<HashMap<RenderTarget, HashSet<RenderTarget>>>>,
 }
 
-impl DependencyCollector {
-    pub fn new(
-        target: RenderTarget,
-        store: Arc<PipelineStore>,
-        hard_deps: Arc<RwLock<HashMap<RenderTarget, HashSet<RenderTarget>>>>,
-        soft_deps: Arc<RwLock<HashMap<RenderTarget, HashSet<RenderTarget>>>>,
-    ) -> Self {
-        Self {
-            target,
-            store,
-            hard_deps,
-            soft_deps,
-        }
+impl Object for RenderContext {
+    fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
+        let key_str = key.as_str()?;
+        let resource = match key_str {
+            "props" => ResourceType::Props,
+            "tasks" => ResourceType::Tasks,
+            other if self.module.modules.contains_key(other) => ResourceType::Modules(other.to_string()),
+            _ => return None,
+        };
+
+        Some(Value::from_object(DependencyCollector {
+            target: RenderTarget {
+                hash: self.origin.hash,
+                resource,
+                name: String::new(),
+            },
+            origin: self.origin.clone(),
+            store: self.store.clone(),
+            resolved: self.resolved.clone(),
+            hard_deps: self.hard_deps.clone(),
+            soft_deps: self.soft_deps.clone(),
+        }))
     }
 }
 
+/// A `"props"`/`"tasks"`/submodule namespace object: resolving `<ns>.<name>`
+/// either returns an already-materialized value (from `resolved`) or records
+/// a dependency edge and hands back the `"<resolving>"` placeholder.
+#[derive(Debug)]
+struct DependencyCollector {
+    /// `(hash, resource)` prefix this collector resolves `name` under; its
+    /// own `name` is unused (always empty -- filled in per lookup below).
+    target: RenderTarget,
+    /// Identity of the target whose body is currently rendering -- the key
+    /// dependency edges are recorded against.
+    origin: RenderTarget,
+    store: Arc<PipelineStore>,
+    resolved: Arc<Mutex<HashMap<RenderTarget, Arc<Resource>>>>,
+    hard_deps: Arc<RwLock<HashMap<RenderTarget, HashSet<RenderTarget>>>>,
+    soft_deps: Arc<RwLock<HashMap<RenderTarget, HashSet<RenderTarget>>>>,
+}
+
 fn grab_use_hash(uses: &Option<UseRef>) -> Option<u64> {
     match uses {
         Some(UseRef::Hash(hash)) => Some(*hash),
@@ -129,7 +167,7 @@ fn check_is_valid_target(store: &PipelineStore, target: &RenderTarget) -> bool {
         ResourceType::Modules(submodule_name) => {
             let submodule = match module.module.modules.get(submodule_name) {
                 Some(sub) => sub,
-                None => return false, 
+                None => return false,
             };
 
             if submodule.tasks.contains_key(&target.name) {
@@ -147,7 +185,7 @@ fn check_is_valid_target(store: &PipelineStore, target: &RenderTarget) -> bool {
     }
 }
 
-impl Object for DependencyCollector  {
+impl Object for DependencyCollector {
     fn get_value(self: &Arc<Self>, key: &minijinja::Value) -> Option<minijinja::Value> {
         let target = RenderTarget {
             hash: self.target.hash,
@@ -155,60 +193,415 @@ impl Object for DependencyCollector  {
             name: key.as_str()?.to_string(),
         };
 
-        
         if !check_is_valid_target(&self.store, &target) {
             return None;
         }
-        
 
-        if let Some(rendered) = self.store.rendered.get(&target) {
-            match rendered {
-                Resource::Prop { value } => {
-                    return Some(value.clone());
-                }
-                Resource::Task { .. } => {
-                    return Some(format!("{}:{}", self.store.aliases.get(&target.hash)?.to_string(), target.name).into());
-                }
-            }
+        // Task references are soft: a `{{ tasks.y }}`/`{{ sub.y }}` embed is
+        // just an `alias:name` locator the generator substitutes later, so
+        // once it's validated it resolves immediately -- it never needs to
+        // wait on the referenced task actually being rendered.
+        if !matches!(target.resource, ResourceType::Props) {
+            self.soft_deps
+                .write()
+                .unwrap()
+                .entry(self.origin.clone())
+                .or_insert_with(HashSet::new)
+                .insert(target.clone());
+
+            let alias = self.store.alias_for_hash(target.hash)?;
+            return Some(Value::from(format!("{}:{}", alias, target.name)));
         }
 
-        let deps_store = if let ResourceType::Props = self.target.resource {
-           &mut self.hard_deps.write().unwrap()
-        } else {
-            &mut self.soft_deps.write().unwrap()
-        };
+        // Props are hard: this lookup's value has to be fully inlined, so it
+        // can only resolve once the dependency has actually been rendered.
+        if let Some(resource) = self.resolved.lock().unwrap().get(&target).cloned() {
+            return match resource.as_ref() {
+                Resource::Prop { value } => Some(value.clone()),
+                Resource::Task { .. } => None,
+            };
+        }
 
-        let entry = deps_store.entry(self.target.clone()).or_insert_with(HashSet::new);
-        entry.insert(target.clone());
+        self.hard_deps
+            .write()
+            .unwrap()
+            .entry(self.origin.clone())
+            .or_insert_with(HashSet::new)
+            .insert(target);
 
         Some(minijinja::Value::from("<resolving>"))
     }
-    
 }
 
-fn render_resource<'a>(
-    env: &minijinja::Environment<'a>,
-    dc: &DependencyCollector,
+/// Renders `body` (a task `cmd` or a string-valued prop) against `target`'s
+/// own `props`/`tasks`/submodule namespace. Used for both passes: during
+/// discovery, not every `Props` lookup is in `resolved` yet, so the output
+/// is a placeholder-laced throwaway whose only purpose is the `hard_deps`/
+/// `soft_deps` edges it records; during resolve, `hard_deps`-topological
+/// order guarantees every `Props` lookup already is, so the same call
+/// produces the real, fully-inlined result.
+fn render_resource(
     target: &RenderTarget,
-) -> Result<Arc<String>, PipelineError> {
-    let rendered = dc.store.rendered.get(target);
-    if let Some(content) = rendered {
-        return Ok(content.clone());
+    body: &str,
+    store: &Arc<PipelineStore>,
+    resolved: &Arc<Mutex<HashMap<RenderTarget, Arc<Resource>>>>,
+    hard_deps: &Arc<RwLock<HashMap<RenderTarget, HashSet<RenderTarget>>>>,
+    soft_deps: &Arc<RwLock<HashMap<RenderTarget, HashSet<RenderTarget>>>>,
+) -> Result<String, PipelineError> {
+    let module = store
+        .metamodules
+        .get(&target.hash)
+        .map(|meta| Arc::new(meta.module.clone()))
+        .ok_or_else(|| PipelineError::Internal(format!("Module {:x} missing", target.hash)))?;
+
+    let mut env = minijinja::Environment::new();
+    env.add_template("main", body)
+        .map_err(|e| PipelineError::Internal(e.to_string()))?;
+
+    let root = Value::from_object(RenderContext {
+        origin: target.clone(),
+        module,
+        store: store.clone(),
+        resolved: resolved.clone(),
+        hard_deps: hard_deps.clone(),
+        soft_deps: soft_deps.clone(),
+    });
+
+    let tmpl = env
+        .get_template("main")
+        .map_err(|e| PipelineError::Internal(e.to_string()))?;
+
+    tmpl.render(root)
+        .map_err(|e| PipelineError::Internal(e.to_string()))
+}
+
+/// A discovered `(RenderTarget, body)` pair, before it's known whether the
+/// target actually needs templating (a non-string prop value doesn't).
+enum PendingTarget {
+    Prop { target: RenderTarget, value: Value },
+    Task { target: RenderTarget, cmd: String },
+}
+
+/// Walks every loaded module's own `props`, `tasks`, and one level of
+/// `modules.*.tasks` into the full set of render targets. Targets reached
+/// only through a `uses` chain aren't re-emitted here -- they're already
+/// covered under the `uses` target's own module hash.
+fn discover_targets(store: &PipelineStore) -> Vec<PendingTarget> {
+    let mut out = Vec::new();
+
+    for (hash, meta) in store.metamodules.iter() {
+        let module = &meta.module;
+
+        if let Some(props) = &module.props {
+            for (name, value) in props {
+                out.push(PendingTarget::Prop {
+                    target: RenderTarget {
+                        hash: *hash,
+                        resource: ResourceType::Props,
+                        name: name.clone(),
+                    },
+                    value: value.clone(),
+                });
+            }
+        }
+
+        for (name, task) in &module.tasks {
+            out.push(PendingTarget::Task {
+                target: RenderTarget {
+                    hash: *hash,
+                    resource: ResourceType::Tasks,
+                    name: name.clone(),
+                },
+                cmd: task.cmd.clone(),
+            });
+        }
+
+        for (sub_name, submodule) in &module.modules {
+            for (name, task) in &submodule.tasks {
+                out.push(PendingTarget::Task {
+                    target: RenderTarget {
+                        hash: *hash,
+                        resource: ResourceType::Modules(sub_name.clone()),
+                        name: name.clone(),
+                    },
+                    cmd: task.cmd.clone(),
+                });
+            }
+        }
     }
 
-    let result = env.render_named_str(&target.to_string(), source, ctx)
+    out
 }
 
-// store, module hash, submodule name, props/tasks, name
+/// Kahn's algorithm over `hard_deps` only -- `soft_deps` (task references)
+/// never constrain render order. Mirrors `graph::topological_order`, keyed
+/// by `RenderTarget` instead of a task hash.
+fn topological_order(
+    nodes: &[RenderTarget],
+    hard_deps: &HashMap<RenderTarget, HashSet<RenderTarget>>,
+) -> Option<Vec<RenderTarget>> {
+    let mut in_degree: HashMap<RenderTarget, usize> = nodes.iter().map(|n| (n.clone(), 0)).collect();
+    let mut dependents: HashMap<RenderTarget, Vec<RenderTarget>> = HashMap::default();
+
+    for (target, deps) in hard_deps {
+        for dep in deps {
+            if let Some(degree) = in_degree.get_mut(target) {
+                *degree += 1;
+            }
+            dependents.entry(dep.clone()).or_default().push(target.clone());
+        }
+    }
+
+    let mut frontier: Vec<RenderTarget> = in_degree
+        .iter()
+        .filter(|(_, degree)| **degree == 0)
+        .map(|(target, _)| target.clone())
+        .collect();
+    frontier.sort();
+    let mut queue: VecDeque<RenderTarget> = frontier.into();
+
+    let mut order: Vec<RenderTarget> = Vec::with_capacity(nodes.len());
+    while let Some(target) = queue.pop_front() {
+        order.push(target.clone());
+
+        if let Some(successors) = dependents.get(&target) {
+            let mut ready: Vec<RenderTarget> = Vec::new();
+            for successor in successors {
+                let degree = in_degree.get_mut(successor).unwrap();
+                *degree -= 1;
+                if *degree == 0 {
+                    ready.push(successor.clone());
+                }
+            }
+            ready.sort();
+            queue.extend(ready);
+        }
+    }
+
+    if order.len() == nodes.len() {
+        Some(order)
+    } else {
+        None
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Color {
+    White,
+    Gray,
+    Black,
+}
+
+/// Finds a back edge in `hard_deps` via DFS with white/gray/black coloring
+/// and renders the offending path as `CyclicDependency`'s member set.
+fn find_cycle(nodes: &[RenderTarget], hard_deps: &HashMap<RenderTarget, HashSet<RenderTarget>>) -> IHashSet<String> {
+    fn visit(
+        target: &RenderTarget,
+        hard_deps: &HashMap<RenderTarget, HashSet<RenderTarget>>,
+        color: &mut HashMap<RenderTarget, Color>,
+        stack: &mut Vec<RenderTarget>,
+    ) -> Option<Vec<RenderTarget>> {
+        color.insert(target.clone(), Color::Gray);
+        stack.push(target.clone());
+
+        if let Some(deps) = hard_deps.get(target) {
+            let mut deps: Vec<RenderTarget> = deps.iter().cloned().collect();
+            deps.sort();
+            for dep in &deps {
+                match color.get(dep).copied().unwrap_or(Color::White) {
+                    Color::White => {
+                        if let Some(cycle) = visit(dep, hard_deps, color, stack) {
+                            return Some(cycle);
+                        }
+                    }
+                    Color::Gray => {
+                        let start = stack.iter().position(|t| t == dep).unwrap();
+                        return Some(stack[start..].to_vec());
+                    }
+                    Color::Black => {}
+                }
+            }
+        }
+
+        stack.pop();
+        color.insert(target.clone(), Color::Black);
+        None
+    }
+
+    let mut color: HashMap<RenderTarget, Color> = HashMap::default();
+    for target in nodes {
+        if color.get(target).copied().unwrap_or(Color::White) == Color::White {
+            if let Some(cycle) = visit(target, hard_deps, &mut color, &mut Vec::new()) {
+                return cycle.iter().map(|t| t.to_string()).collect();
+            }
+        }
+    }
+
+    IHashSet::default()
+}
 
 impl Pipeline {
-    pub fn render(&self) -> Result<(), PipelineError> {}
-
-    fn render_body(&self, body: &str) -> Result<String, PipelineError> {
-        /*
-           {{ tasks.name }}
-           {{ props.name }}
-           {{ module.name}}
-        */
+    /// Fully materializes every prop and task body across all loaded modules
+    /// into `self.stores.rendered`, as a two-phase resolver:
+    ///
+    /// 1. **Discover**: render each target's body once against a
+    ///    `DependencyCollector`, which records every `props.x` it touches as
+    ///    a `hard_dep` (must be inlined first) and every `tasks.y`/`sub.y` as
+    ///    a `soft_dep` (left as an `alias:name` reference, never ordering).
+    /// 2. **Resolve**: topologically sort by `hard_deps` alone -- detecting
+    ///    a cycle via `PipelineError::CyclicDependency` -- then render each
+    ///    target again, in that order, caching the real result as it goes.
+    pub fn render(&mut self) -> Result<(), PipelineError> {
+        let store_arc = Arc::new(std::mem::take(&mut self.stores));
+
+        let resolved: Arc<Mutex<HashMap<RenderTarget, Arc<Resource>>>> = Arc::new(Mutex::new(
+            store_arc.rendered.iter().map(|(target, resource)| (target.clone(), resource.clone())).collect(),
+        ));
+        let hard_deps: Arc<RwLock<HashMap<RenderTarget, HashSet<RenderTarget>>>> = Arc::new(RwLock::new(HashMap::default()));
+        let soft_deps: Arc<RwLock<HashMap<RenderTarget, HashSet<RenderTarget>>>> = Arc::new(RwLock::new(HashMap::default()));
+
+        // A non-string prop value (bool, int, ...) has no template to parse
+        // and no deps of its own; seed it as already-resolved so discovery
+        // never has to touch it.
+        let mut bodies: HashMap<RenderTarget, String> = HashMap::default();
+        for pending in discover_targets(&store_arc) {
+            match pending {
+                PendingTarget::Prop { target, value } => {
+                    if let Some(body) = value.as_str() {
+                        bodies.insert(target, body.to_string());
+                    } else {
+                        resolved
+                            .lock()
+                            .unwrap()
+                            .insert(target, Arc::new(Resource::Prop { value }));
+                    }
+                }
+                PendingTarget::Task { target, cmd } => {
+                    bodies.insert(target, cmd);
+                }
+            }
+        }
+        let targets: Vec<RenderTarget> = bodies.keys().cloned().collect();
+
+        // Phase 1: discover hard/soft deps; the rendered output itself is
+        // thrown away (it's placeholder-laced wherever a hard dep isn't
+        // resolved yet).
+        for (target, body) in &bodies {
+            render_resource(target, body, &store_arc, &resolved, &hard_deps, &soft_deps)?;
+        }
+
+        let order = {
+            let hard = hard_deps.read().unwrap();
+            topological_order(&targets, &hard).ok_or_else(|| PipelineError::CyclicDependency(find_cycle(&targets, &hard)))?
+        };
+
+        // Phase 2: resolve for real, in dependency order, caching each
+        // target's `Resource` as soon as it's rendered so later targets can
+        // inline it.
+        for target in order {
+            if resolved.lock().unwrap().contains_key(&target) {
+                continue;
+            }
+
+            let body = bodies
+                .get(&target)
+                .expect("topological_order only returns discovered targets");
+            let rendered = render_resource(&target, body, &store_arc, &resolved, &hard_deps, &soft_deps)?;
+
+            let resource = match target.resource {
+                ResourceType::Props => Resource::Prop {
+                    value: Value::from(rendered),
+                },
+                _ => {
+                    let module = store_arc
+                        .metamodules
+                        .get(&target.hash)
+                        .ok_or_else(|| PipelineError::Internal(format!("Module {:x} missing", target.hash)))?;
+                    Resource::Task {
+                        cmd: body.clone(),
+                        props: module.module.props.clone().unwrap_or_default(),
+                        rendered,
+                    }
+                }
+            };
+
+            resolved.lock().unwrap().insert(target, Arc::new(resource));
+        }
+
+        self.stores = Arc::try_unwrap(store_arc).unwrap_or_default();
+        for (target, resource) in Arc::try_unwrap(resolved).unwrap_or_default().into_inner().unwrap_or_default() {
+            self.stores.rendered.insert_as_arc(target, resource);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(hash: u64, name: &str) -> RenderTarget {
+        RenderTarget {
+            hash,
+            resource: ResourceType::Props,
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_topological_order_orders_dependencies_first() {
+        let a = target(1, "a");
+        let b = target(1, "b");
+        let c = target(1, "c");
+
+        // c depends on b, b depends on a.
+        let mut hard_deps: HashMap<RenderTarget, HashSet<RenderTarget>> = HashMap::default();
+        hard_deps.insert(b.clone(), HashSet::from_iter([a.clone()]));
+        hard_deps.insert(c.clone(), HashSet::from_iter([b.clone()]));
+
+        let order = topological_order(&[c.clone(), b.clone(), a.clone()], &hard_deps)
+            .expect("acyclic graph should resolve");
+
+        let pos = |t: &RenderTarget| order.iter().position(|o| o == t).unwrap();
+        assert!(pos(&a) < pos(&b));
+        assert!(pos(&b) < pos(&c));
+    }
+
+    #[test]
+    fn test_topological_order_none_on_cycle() {
+        let a = target(1, "a");
+        let b = target(1, "b");
+
+        let mut hard_deps: HashMap<RenderTarget, HashSet<RenderTarget>> = HashMap::default();
+        hard_deps.insert(a.clone(), HashSet::from_iter([b.clone()]));
+        hard_deps.insert(b.clone(), HashSet::from_iter([a.clone()]));
+
+        assert!(topological_order(&[a, b], &hard_deps).is_none());
+    }
+
+    #[test]
+    fn test_find_cycle_reports_the_cycle_members() {
+        let a = target(1, "a");
+        let b = target(1, "b");
+
+        let mut hard_deps: HashMap<RenderTarget, HashSet<RenderTarget>> = HashMap::default();
+        hard_deps.insert(a.clone(), HashSet::from_iter([b.clone()]));
+        hard_deps.insert(b.clone(), HashSet::from_iter([a.clone()]));
+
+        let cycle = find_cycle(&[a.clone(), b.clone()], &hard_deps);
+        assert!(cycle.contains(&a.to_string()));
+        assert!(cycle.contains(&b.to_string()));
+    }
+
+    #[test]
+    fn test_find_cycle_empty_when_acyclic() {
+        let a = target(1, "a");
+        let b = target(1, "b");
+
+        let mut hard_deps: HashMap<RenderTarget, HashSet<RenderTarget>> = HashMap::default();
+        hard_deps.insert(b.clone(), HashSet::from_iter([a.clone()]));
+
+        assert!(find_cycle(&[a, b], &hard_deps).is_empty());
     }
 }
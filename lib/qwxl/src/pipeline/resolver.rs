@@ -1,19 +1,82 @@
-use ahash::HashSet;
+use ahash::{HashMap, HashSet};
 use minijinja::{Environment, Error, ErrorKind, State, Value, value::Object};
 use std::sync::{Arc, Mutex};
 
 use crate::pipeline::{
     Pipeline, PipelineStore,
-    ast::{Module, Props, UseRef},
+    ast::{IHashSet, Module, Param, ParamType, Props, Task, UseRef},
     error::PipelineError,
 };
 
-#[derive(Default, Debug, Clone, serde::Serialize)]
+#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct TaskNode {
     pub cmd: String,
     pub deps: HashSet<u64>,
     pub hash: u64,
     pub alias: String,
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    /// Declared parameter schema, carried through so `ShellGenerator` can emit
+    /// positional bindings (and, for root tasks, CLI flags) without re-parsing
+    /// the source `Task`.
+    pub args: Vec<Param>,
+    /// Whether this task opted into namespace-isolated execution via its
+    /// `sandbox: true` annotation. Only honored by `Driver` when
+    /// `Config.sandbox` is also enabled.
+    pub sandbox: bool,
+}
+
+/// Coerces a single `minijinja::Value` to the shape `ty` expects, e.g. turning
+/// the string `"3"` into the integer `3` for a declared `ParamType::Int`.
+/// Values that already have the right Rust-level shape pass through unchanged.
+/// Returns `InvalidArgument(task_name, param.name)` when `value` can't be
+/// coerced to `ty`.
+fn coerce_param(ty: ParamType, task_name: &str, name: &str, value: Value) -> Result<Value, PipelineError> {
+    match ty {
+        ParamType::String | ParamType::Path => Ok(Value::from(value.to_string())),
+        ParamType::Int => value
+            .to_string()
+            .trim()
+            .parse::<i64>()
+            .map(Value::from)
+            .map_err(|_| PipelineError::InvalidArgument(task_name.to_string(), name.to_string())),
+        ParamType::Bool => match value.as_str() {
+            Some("true") => Ok(Value::from(true)),
+            Some("false") => Ok(Value::from(false)),
+            Some(_) => Err(PipelineError::InvalidArgument(
+                task_name.to_string(),
+                name.to_string(),
+            )),
+            None => Ok(Value::from(value.is_true())),
+        },
+    }
+}
+
+/// Applies each declared arg's default (when the caller didn't supply one),
+/// rejects a missing `required` arg with `InvalidArgRef`, and type-coerces
+/// whatever value ends up bound to that name, so `props.x` always sees a
+/// value of the declared shape.
+fn apply_arg_schema(task_name: &str, task_def: &Task, mut props: Props) -> Result<Props, PipelineError> {
+    for param in &task_def.args {
+        let value = match props.get(&param.name) {
+            Some(v) => v.clone(),
+            None => match &param.default {
+                Some(default) => Value::from(default.clone()),
+                None if param.required => {
+                    return Err(PipelineError::InvalidArgRef(
+                        task_name.to_string(),
+                        param.name.clone(),
+                    ));
+                }
+                None => continue,
+            },
+        };
+        props.insert(
+            param.name.clone(),
+            coerce_param(param.ty, task_name, &param.name, value)?,
+        );
+    }
+    Ok(props)
 }
 
 fn to_jinja_err(e: impl std::fmt::Display) -> Error {
@@ -24,15 +87,39 @@ fn to_jinja_err(e: impl std::fmt::Display) -> Error {
 struct ModuleContext {
     module: Arc<Module>,
     store: Arc<PipelineStore>,
-    visited: Arc<Mutex<HashSet<u64>>>,
+    /// Where a task resolved while rendering *this* context's owner reports
+    /// its hash back to -- i.e. the owner's own direct-dependency set, freshly
+    /// scoped per `compile_task_internal` call rather than shared globally, so
+    /// one task's dependency discovery can never leak into an unrelated one's.
+    direct_deps: Arc<Mutex<HashSet<u64>>>,
+    resolved: Arc<Mutex<HashMap<u64, Arc<TaskNode>>>>,
+    /// `(module_hash, task_name)` identities currently being resolved on this
+    /// call stack, used to detect a task that transitively resolves back into
+    /// itself (directly, or through a `uses` chain) before it overflows the
+    /// stack.
+    resolving: Arc<Mutex<IHashSet<String>>>,
+    /// Identity of `module`, used together with a task name to form the
+    /// `resolving` stack entries. Inline submodules accessed by name have no
+    /// stable identity of their own and inherit their parent's.
+    module_hash: u64,
 }
 
 impl ModuleContext {
-    fn new(module: Module, store: Arc<PipelineStore>, visited: Arc<Mutex<HashSet<u64>>>) -> Self {
+    fn new(
+        module: Module,
+        store: Arc<PipelineStore>,
+        direct_deps: Arc<Mutex<HashSet<u64>>>,
+        resolved: Arc<Mutex<HashMap<u64, Arc<TaskNode>>>>,
+        resolving: Arc<Mutex<IHashSet<String>>>,
+        module_hash: u64,
+    ) -> Self {
         Self {
             module: Arc::new(module),
             store,
-            visited,
+            direct_deps,
+            resolved,
+            resolving,
+            module_hash,
         }
     }
 
@@ -40,11 +127,28 @@ impl ModuleContext {
         Self {
             module: Arc::new(module.clone()),
             store: parent.store.clone(),
-            visited: parent.visited.clone(),
+            direct_deps: parent.direct_deps.clone(),
+            resolved: parent.resolved.clone(),
+            resolving: parent.resolving.clone(),
+            module_hash: parent.module_hash,
         }
     }
 }
 
+/// Removes this call's `(module_hash, task_name)` identity from the active
+/// resolution stack once `compile_task_internal` returns, via whichever
+/// path it returns by.
+struct ResolutionGuard {
+    stack: Arc<Mutex<IHashSet<String>>>,
+    identity: String,
+}
+
+impl Drop for ResolutionGuard {
+    fn drop(&mut self) {
+        self.stack.lock().unwrap().shift_remove(&self.identity);
+    }
+}
+
 impl Object for ModuleContext {
     fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
         let key_str = key.as_str()?;
@@ -119,8 +223,14 @@ impl Object for TaskScopeProxy {
                         props: merged_props,
                         ..Default::default()
                     };
-                    let virtual_ctx =
-                        ModuleContext::new(virtual_module, store.clone(), ctx.visited.clone());
+                    let virtual_ctx = ModuleContext::new(
+                        virtual_module,
+                        store.clone(),
+                        ctx.direct_deps.clone(),
+                        ctx.resolved.clone(),
+                        ctx.resolving.clone(),
+                        *target_hash,
+                    );
                     let node = compile_task_internal(virtual_ctx, "main".into(), Props::default())
                         .map_err(to_jinja_err)?;
                     return Ok(Value::from(node.cmd.clone()));
@@ -191,6 +301,25 @@ fn compile_task_internal(
         )));
     };
 
+    // Guard against a task that transitively resolves back into itself,
+    // directly or through a `uses` chain, before it recurses into a stack
+    // overflow. The guard is dropped (popping this identity) on every
+    // return path below, including the early `uses`-virtual recursion.
+    let identity = format!("{:x}:{}", ctx.module_hash, task_name);
+    {
+        let mut stack = ctx.resolving.lock().unwrap();
+        if stack.contains(&identity) {
+            let mut chain = stack.clone();
+            chain.insert(identity.clone());
+            return Err(PipelineError::CyclicDependency(chain));
+        }
+        stack.insert(identity.clone());
+    }
+    let _guard = ResolutionGuard {
+        stack: ctx.resolving.clone(),
+        identity: identity.clone(),
+    };
+
     if let Some(UseRef::Hash(target_hash)) = &task_def.uses {
         // Merge props: Call Args > Task Props
         let mut merged_props = task_def.props.clone();
@@ -203,8 +332,14 @@ fn compile_task_internal(
             ..Default::default()
         };
 
-        let virtual_ctx =
-            ModuleContext::new(virtual_module, ctx.store.clone(), ctx.visited.clone());
+        let virtual_ctx = ModuleContext::new(
+            virtual_module,
+            ctx.store.clone(),
+            ctx.direct_deps.clone(),
+            ctx.resolved.clone(),
+            ctx.resolving.clone(),
+            *target_hash,
+        );
 
         // Recurse: Call "main" (or your preferred default entry) on the target library
         return compile_task_internal(virtual_ctx, "main".to_string(), Props::default());
@@ -221,33 +356,51 @@ fn compile_task_internal(
     effective_props.extend(ctx.module.props.clone());
     effective_props.extend(task_def.props.clone());
     effective_props.extend(call_props.clone());
+    // Fill in declared-arg defaults and type-check/coerce whatever ended up
+    // bound to each declared name, so the same task called with different
+    // argument values hashes (and therefore compiles) distinctly.
+    effective_props = apply_arg_schema(&task_name, &task_def, effective_props)?;
 
     // ... (rest of the function: hashing, cache check, env creation, rendering) ...
-    let mut hasher = ahash::AHasher::default();
-    use std::hash::{Hash, Hasher};
-    task_def.cmd.hash(&mut hasher);
-    serde_json::to_string(&effective_props)
-        .unwrap()
-        .hash(&mut hasher);
-    let cache_key = hasher.finish();
-
-    if let Some(node) = ctx.store.tasks.get(&cache_key) {
-        ctx.visited.lock().unwrap().insert(cache_key);
+    // A task's identity is its command plus its fully resolved props, encoded
+    // canonically (`effective_props` is an `IndexMap`, so key order is stable
+    // insertion order) and hashed with `str_hash`'s SHA-256-backed digest, so
+    // the same task+props always yields the same cache key regardless of
+    // process or machine.
+    let canonical = format!(
+        "{}\u{0}{}",
+        task_def.cmd,
+        serde_json::to_string(&effective_props).unwrap()
+    );
+    let cache_key = crate::pipeline::parser::str_hash(&canonical);
+
+    if let Some(node) = ctx.resolved.lock().unwrap().get(&cache_key) {
+        ctx.direct_deps.lock().unwrap().insert(cache_key);
         return Ok(node.clone());
     }
 
+    // Direct dependencies discovered while rendering *this* task's own body
+    // (each `tasks.foo()` call, or `uses:` target, reports its hash here) --
+    // freshly scoped to this call so a sibling task resolved elsewhere in the
+    // same tree can never leak into this node's `deps`.
+    let own_deps: Arc<Mutex<HashSet<u64>>> = Arc::new(Mutex::new(HashSet::default()));
+    let render_ctx = ModuleContext {
+        direct_deps: own_deps.clone(),
+        ..ctx.clone()
+    };
+
     let mut env = Environment::new();
     env.add_template("main", &task_def.cmd)
         .map_err(|e| PipelineError::Internal(e.to_string()))?;
 
     let root = Value::from_object(RootContext {
         props: Value::from_object(PropsContext {
-            ctx: ctx.clone(),
+            ctx: render_ctx.clone(),
             task_props: Some(task_def.props.clone()),
-            call_props: Some(call_props),
+            call_props: Some(effective_props.clone()),
         }),
-        tasks: Value::from_object(TaskScopeProxy { ctx: ctx.clone() }),
-        module_ctx: ctx.clone(),
+        tasks: Value::from_object(TaskScopeProxy { ctx: render_ctx.clone() }),
+        module_ctx: render_ctx.clone(),
     });
 
     let tmpl = env
@@ -257,13 +410,36 @@ fn compile_task_internal(
         .render(root)
         .map_err(|e| PipelineError::Internal(e.to_string()))?;
 
+    // Explicit `needs:` entries are resolved the same way a called sibling
+    // task is (same module, or its `uses` target), recursing through
+    // `compile_task_internal` so a `needs` chain benefits from the same
+    // cycle guard as a `uses`/call chain instead of needing its own.
+    let mut deps = own_deps.lock().unwrap().clone();
+    for needed in &task_def.needs {
+        let needed_exists = ctx.module.tasks.contains_key(needed)
+            || matches!(ctx.module.uses, Some(UseRef::Hash(h))
+                if ctx.store.metamodules.get(&h).is_some_and(|m| m.module.tasks.contains_key(needed)));
+        if !needed_exists {
+            return Err(PipelineError::TaskNotFound(needed.clone()));
+        }
+        let needed_node = compile_task_internal(render_ctx.clone(), needed.clone(), Props::default())?;
+        deps.insert(needed_node.hash);
+    }
+
     let node = Arc::new(TaskNode {
         cmd: rendered,
-        deps: ctx.visited.lock().unwrap().clone(),
+        deps,
         hash: cache_key,
         alias: task_name,
+        inputs: task_def.inputs.clone(),
+        outputs: task_def.outputs.clone(),
+        args: task_def.args.clone(),
+        sandbox: task_def.sandbox,
     });
 
+    ctx.resolved.lock().unwrap().insert(cache_key, node.clone());
+    ctx.direct_deps.lock().unwrap().insert(cache_key);
+
     Ok(node)
 }
 
@@ -294,6 +470,18 @@ impl Object for RootContext {
 
 impl Pipeline {
     pub fn resolve_task(&mut self, alias: &str, task: &str) -> Result<TaskNode, PipelineError> {
+        self.resolve_task_with_overrides(alias, task, &[])
+    }
+
+    /// Like `resolve_task`, but layers `overrides` (e.g. the CLI's `--with
+    /// key=value` flags) on top of the task's declared props before
+    /// compiling, the same way a `{{ tasks.foo(key=value) }}` call would.
+    pub fn resolve_task_with_overrides(
+        &mut self,
+        alias: &str,
+        task: &str,
+        overrides: &[(String, String)],
+    ) -> Result<TaskNode, PipelineError> {
         let store_arc = Arc::new(std::mem::take(&mut self.stores));
         let target_hash = store_arc
             .aliases
@@ -307,12 +495,33 @@ impl Pipeline {
             .get(target_hash)
             .ok_or(PipelineError::Internal("Module missing".into()))?;
 
-        let visited = Arc::new(Mutex::new(HashSet::default()));
-        let ctx = ModuleContext::new(meta.module.clone(), store_arc.clone(), visited);
+        let direct_deps = Arc::new(Mutex::new(HashSet::default()));
+        let resolved = Arc::new(Mutex::new(HashMap::default()));
+        let resolving = Arc::new(Mutex::new(IHashSet::default()));
+        let ctx = ModuleContext::new(
+            meta.module.clone(),
+            store_arc.clone(),
+            direct_deps,
+            resolved.clone(),
+            resolving,
+            **target_hash,
+        );
+
+        let mut call_props = Props::default();
+        for (key, value) in overrides {
+            call_props.insert(key.clone(), Value::from(value.clone()));
+        }
 
-        let node = compile_task_internal(ctx, task.to_string(), Props::default())?;
+        let node = compile_task_internal(ctx, task.to_string(), call_props)?;
 
         self.stores = Arc::try_unwrap(store_arc).unwrap_or_default();
+        for (hash, task_node) in Arc::try_unwrap(resolved)
+            .unwrap_or_default()
+            .into_inner()
+            .unwrap_or_default()
+        {
+            self.stores.tasks.insert_as_arc(hash, task_node);
+        }
         Ok((*node).clone())
     }
 }
@@ -321,7 +530,8 @@ impl Pipeline {
 mod tests {
     use crate::pipeline::{
         Config, Pipeline,
-        ast::{MetaModule, Module, Props, Task, UseRef},
+        ast::{MetaModule, Module, Param, ParamType, Props, Task, UseRef},
+        error::PipelineError,
     };
     use ahash::RandomState;
 
@@ -355,7 +565,11 @@ mod tests {
     }
 
     fn register_module(p: &mut Pipeline, alias: &str, module: Module, hash: u64) {
-        let meta = MetaModule { module, hash };
+        let meta = MetaModule {
+            module,
+            hash,
+            ..Default::default()
+        };
         p.stores.metamodules.insert(hash, meta); // Insert raw (Store auto-wraps in Arc)
         p.stores.aliases.insert(alias.to_string(), hash);
     }
@@ -463,6 +677,7 @@ mod tests {
             uses: Some(UseRef::Hash(lib_hash)),
             props: create_props(&[("mode", "sugar")]),
             cmd: "this should be ignored".to_string(),
+            ..Default::default()
         };
         consumer_mod
             .tasks
@@ -476,6 +691,75 @@ mod tests {
         assert_eq!(result.cmd, "Library Action: sugar");
     }
 
+    #[test]
+    fn test_cyclic_uses_detected() {
+        let mut p = create_pipeline();
+
+        let hash = 60;
+        let mut module = create_module(&[], &[]);
+        // "main" uses its own module, so resolving it recurses straight back
+        // into "main" instead of ever reaching a base case.
+        module.tasks.insert(
+            "main".to_string(),
+            Task {
+                uses: Some(UseRef::Hash(hash)),
+                cmd: "unreachable".to_string(),
+                ..Default::default()
+            },
+        );
+        register_module(&mut p, "root", module, hash);
+
+        let err = p.resolve_task("root", "main").unwrap_err();
+        assert!(matches!(err, PipelineError::CyclicDependency(_)));
+    }
+
+    #[test]
+    fn test_explicit_needs_resolves_into_deps() {
+        let mut p = create_pipeline();
+
+        let mut module = create_module(
+            &[("setup", "echo setup"), ("build", "echo build")],
+            &[],
+        );
+        module.tasks.get_mut("build").unwrap().needs = vec!["setup".to_string()];
+        register_module(&mut p, "root", module, 70);
+
+        let result = p.resolve_task("root", "build").unwrap();
+        let setup_hash = p
+            .resolve_task("root", "setup")
+            .unwrap()
+            .hash;
+        assert!(result.deps.contains(&setup_hash));
+    }
+
+    #[test]
+    fn test_needs_unknown_task_errors() {
+        let mut p = create_pipeline();
+
+        let mut module = create_module(&[("build", "echo build")], &[]);
+        module.tasks.get_mut("build").unwrap().needs = vec!["missing".to_string()];
+        register_module(&mut p, "root", module, 71);
+
+        let err = p.resolve_task("root", "build").unwrap_err();
+        assert!(matches!(err, PipelineError::TaskNotFound(name) if name == "missing"));
+    }
+
+    #[test]
+    fn test_cyclic_needs_detected() {
+        let mut p = create_pipeline();
+
+        let mut module = create_module(
+            &[("a", "echo a"), ("b", "echo b")],
+            &[],
+        );
+        module.tasks.get_mut("a").unwrap().needs = vec!["b".to_string()];
+        module.tasks.get_mut("b").unwrap().needs = vec!["a".to_string()];
+        register_module(&mut p, "root", module, 72);
+
+        let err = p.resolve_task("root", "a").unwrap_err();
+        assert!(matches!(err, PipelineError::CyclicDependency(_)));
+    }
+
     #[test]
     fn test_task_calling_task_with_args() {
         let mut p = create_pipeline();
@@ -494,4 +778,76 @@ mod tests {
         let result = p.resolve_task("root", "caller").unwrap();
         assert_eq!(result.cmd, "Success");
     }
+
+    #[test]
+    fn test_missing_required_arg_errors() {
+        let mut p = create_pipeline();
+
+        let mut module = create_module(&[("build", "{{ props.profile }}")], &[]);
+        module.tasks.get_mut("build").unwrap().args = vec![Param {
+            name: "profile".to_string(),
+            ty: ParamType::String,
+            default: None,
+            required: true,
+        }];
+        register_module(&mut p, "root", module, 80);
+
+        let err = p.resolve_task("root", "build").unwrap_err();
+        assert!(matches!(
+            err,
+            PipelineError::InvalidArgRef(task, name) if task == "build" && name == "profile"
+        ));
+    }
+
+    #[test]
+    fn test_invalid_int_arg_errors() {
+        let mut p = create_pipeline();
+
+        let mut module = create_module(&[("build", "{{ props.retries }}")], &[]);
+        module.tasks.get_mut("build").unwrap().args = vec![Param {
+            name: "retries".to_string(),
+            ty: ParamType::Int,
+            default: Some("1".to_string()),
+            required: false,
+        }];
+        register_module(&mut p, "root", module, 81);
+
+        let err = p
+            .resolve_task_with_overrides(
+                "root",
+                "build",
+                &[("retries".to_string(), "not-a-number".to_string())],
+            )
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            PipelineError::InvalidArgument(task, name) if task == "build" && name == "retries"
+        ));
+    }
+
+    #[test]
+    fn test_invalid_bool_arg_errors() {
+        let mut p = create_pipeline();
+
+        let mut module = create_module(&[("build", "{{ props.verbose }}")], &[]);
+        module.tasks.get_mut("build").unwrap().args = vec![Param {
+            name: "verbose".to_string(),
+            ty: ParamType::Bool,
+            default: Some("false".to_string()),
+            required: false,
+        }];
+        register_module(&mut p, "root", module, 82);
+
+        let err = p
+            .resolve_task_with_overrides(
+                "root",
+                "build",
+                &[("verbose".to_string(), "maybe".to_string())],
+            )
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            PipelineError::InvalidArgument(task, name) if task == "build" && name == "verbose"
+        ));
+    }
 }